@@ -0,0 +1,133 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::ext::IdentExt;
+use syn::{parse_macro_input, Data, DeriveInput, Field, Fields, LitStr, Path};
+
+/// Derive `rdbc2::dbc::FromRow` for a struct with named fields, reading each field from the
+/// column of the same name. Fields accept the `#[rdbc(...)]` attributes:
+///
+/// - `rename = "column"` reads the field from another column
+/// - `flatten` reads the field as a nested struct from the same row
+/// - `default` or `default = "path::to::function"` uses a default value when the row has no
+///   such column
+#[proc_macro_derive(FromRow, attributes(rdbc))]
+pub fn derive_from_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_from_row(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_from_row(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "FromRow can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "FromRow can only be derived for structs",
+            ))
+        }
+    };
+    let initializers = fields
+        .iter()
+        .map(field_initializer)
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::rdbc2::dbc::FromRow for #name #ty_generics #where_clause {
+            fn from_row(
+                row: &::rdbc2::dbc::Row,
+            ) -> ::std::result::Result<Self, ::rdbc2::dbc::Error> {
+                ::std::result::Result::Ok(Self {
+                    #(#initializers,)*
+                })
+            }
+        }
+    })
+}
+
+#[derive(Default)]
+struct FieldAttributes {
+    rename: Option<LitStr>,
+    flatten: bool,
+    default: Option<Option<Path>>,
+}
+
+fn field_attributes(field: &Field) -> syn::Result<FieldAttributes> {
+    let mut attributes = FieldAttributes::default();
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("rdbc"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                attributes.rename = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("flatten") {
+                attributes.flatten = true;
+            } else if meta.path.is_ident("default") {
+                let function = if meta.input.peek(syn::Token![=]) {
+                    Some(meta.value()?.parse::<LitStr>()?.parse()?)
+                } else {
+                    None
+                };
+                attributes.default = Some(function);
+            } else {
+                return Err(meta.error("unsupported rdbc attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    if attributes.flatten && (attributes.rename.is_some() || attributes.default.is_some()) {
+        return Err(syn::Error::new_spanned(
+            field,
+            "a flattened field cannot be renamed or have a default",
+        ));
+    }
+    Ok(attributes)
+}
+
+fn field_initializer(field: &Field) -> syn::Result<TokenStream2> {
+    let attributes = field_attributes(field)?;
+    let ident = field
+        .ident
+        .as_ref()
+        .expect("named fields have an identifier");
+    let ty = &field.ty;
+    if attributes.flatten {
+        return Ok(quote! {
+            #ident: <#ty as ::rdbc2::dbc::FromRow>::from_row(row)?
+        });
+    }
+
+    let column = attributes
+        .rename
+        .unwrap_or_else(|| LitStr::new(&ident.unraw().to_string(), ident.span()));
+    let value = quote! { row.get::<#ty>(#column)? };
+    Ok(match attributes.default {
+        None => quote! { #ident: #value },
+        Some(default) => {
+            let default = match default {
+                Some(function) => quote! { #function() },
+                None => quote! { ::std::default::Default::default() },
+            };
+            quote! {
+                #ident: match row.get_column_by_name(#column) {
+                    ::std::option::Option::Some(_) => #value,
+                    ::std::option::Option::None => #default,
+                }
+            }
+        }
+    })
+}
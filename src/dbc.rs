@@ -1,29 +1,102 @@
 use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
+use sqlparser::dialect::{Dialect, GenericDialect};
 
+mod async_database;
+mod binary;
+mod deserialize;
+mod driver;
+mod error;
+mod from_row;
+mod from_value;
+mod json;
+#[cfg(feature = "mysql")]
 mod mysql;
+mod options;
+mod pool;
+#[cfg(feature = "postgres")]
+mod postgres;
+mod sql;
+#[cfg(feature = "sqlite")]
 mod sqlite;
 
-pub type Error = Box<dyn std::error::Error + Send + Sync>;
+pub use async_database::{AsyncConnection, AsyncDatabase};
+pub use binary::{Decoder, Encoder};
+pub use driver::{register_driver, Driver, DriverRegistry};
+pub use error::{ConstraintKind, DatabaseError, Error};
+pub use from_row::FromRow;
+pub use from_value::{ColumnIndex, FromValue};
+pub use json::JsonFormat;
+pub use options::{
+    ConnectOptions, MySqlOptions, PostgresOptions, PostgresSslMode, SqliteCache, SqliteJournalMode,
+    SqliteMode, SqliteOptions,
+};
+pub use pool::{Pool, PoolBuilder, PooledConnection};
+#[cfg(feature = "derive")]
+pub use rdbc2_derive::FromRow;
 
 pub trait Connection {
-    fn execute(&mut self, query: &str) -> Result<QueryResult, Error>;
+    fn execute(&mut self, query: &str) -> Result<QueryResult, Error> {
+        self.execute_with_params(query, &[])
+    }
+    /// Execute a query with `?` placeholders bound to the given parameters by the database
+    fn execute_with_params(&mut self, query: &str, params: &[Value]) -> Result<QueryResult, Error> {
+        self.prepare(query)?.execute(params)
+    }
+    /// Execute a query and stream its rows as they are read from the database
+    fn query_iter(&mut self, query: &str, params: &[Value]) -> Result<RowCursor<'_>, Error>;
+    /// Prepare a query once so that it can be executed many times
+    fn prepare(&mut self, query: &str) -> Result<Box<dyn Statement + '_>, Error>;
+    /// Begin a transaction which is rolled back when dropped without being committed
+    fn transaction(&mut self) -> Result<Box<dyn TransactionConnection + '_>, Error>;
+    /// The SQL dialect used to locate placeholders in queries
+    fn dialect(&self) -> &'static dyn Dialect {
+        &GenericDialect {}
+    }
+    /// Check that the connection is still usable
+    fn ping(&mut self) -> Result<(), Error> {
+        self.execute("SELECT 1").map(|_| ())
+    }
+}
+
+pub trait TransactionConnection: Connection {
+    fn commit(self: Box<Self>) -> Result<(), Error>;
+    fn rollback(self: Box<Self>) -> Result<(), Error>;
+    /// Start a savepoint, which is rolled back to when dropped without being committed
+    fn savepoint(&mut self, name: &str) -> Result<Box<dyn TransactionConnection + '_>, Error>;
+}
+
+pub trait Statement {
+    /// Execute the statement with its `?` placeholders bound to the given parameters
+    fn execute(&mut self, params: &[Value]) -> Result<QueryResult, Error>;
+    /// Execute the statement and stream its rows as they are read from the database
+    fn query(&mut self, params: &[Value]) -> Result<RowCursor<'_>, Error>;
+    fn parameter_count(&self) -> usize;
+    /// The columns of the rows returned by the statement, empty if it returns no rows
+    fn columns(&self) -> &[Column];
 }
 
 pub struct Database {
-    pub(crate) connection: Box<dyn Connection>,
+    pub(crate) connection: Box<dyn Connection + Send>,
 }
 
 impl Database {
+    /// Connect with the driver registered in the global DriverRegistry for the URL scheme
     pub fn new(url: &str) -> Result<Self, Error> {
-        let connection = match url {
-            url if url.starts_with("mysql://") => mysql::MySQLConnection::get_connection(url)?,
-            url if url.starts_with("sqlite://") => sqlite::SQLiteConnection::get_connection(url)?,
-            _ => return Err("Unsupported dbc type".into()),
-        };
+        // The registry is not locked while connecting, drivers may take long or register drivers
+        let driver = DriverRegistry::global()
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .resolve(url)?;
+        driver::connect(driver, url)
+    }
 
-        Ok(Database { connection })
+    /// Connect with typed options, e.g. to pass credentials without encoding them into a URL
+    pub fn connect(options: impl Into<ConnectOptions>) -> Result<Self, Error> {
+        Ok(Database {
+            connection: open_connection(&options.into())?,
+        })
     }
 
     pub fn execute_query(&mut self, query: &str) -> Result<QueryResult, Error> {
@@ -33,21 +106,228 @@ impl Database {
     pub fn execute_query_with_params(
         &mut self,
         query: &str,
-        params: &[&str],
+        params: &[Value],
+    ) -> Result<QueryResult, Error> {
+        self.connection.execute_with_params(query, params)
+    }
+
+    /// Execute a query with `:name`, `$name` or `@name` placeholders bound to the given parameters
+    pub fn execute_query_with_named_params(
+        &mut self,
+        query: &str,
+        params: &[(&str, Value)],
+    ) -> Result<QueryResult, Error> {
+        let (query, params) = sql::bind_named_params(self.connection.dialect(), query, params)?;
+        self.connection.execute_with_params(&query, &params)
+    }
+
+    /// Execute a query and serialize its result into JSON, in the lossless `JsonFormat::Tagged`
+    pub fn execute_query_and_serialize(&mut self, query: &str) -> Result<String, Error> {
+        self.execute_query(query)?.to_json(JsonFormat::Tagged)
+    }
+
+    /// Execute a query and encode its result in the versioned binary format of
+    /// `QueryResult::to_bytes`, which `QueryResult::from_bytes` decodes back
+    pub fn execute_query_and_serialize_raw(&mut self, query: &str) -> Result<Vec<u8>, Error> {
+        self.execute_query(query)?.to_bytes()
+    }
+
+    pub fn execute_query_and_serialize_with_params(
+        &mut self,
+        query: &str,
+        params: &[Value],
+    ) -> Result<String, Error> {
+        self.execute_query_with_params(query, params)?
+            .to_json(JsonFormat::Tagged)
+    }
+
+    /// Execute a query and convert each of its rows, e.g. into a struct deriving FromRow
+    pub fn query_as<T: FromRow>(&mut self, query: &str, params: &[Value]) -> Result<Vec<T>, Error> {
+        self.connection
+            .query_iter(query, params)?
+            .map(|row| T::from_row(&row?))
+            .collect()
+    }
+
+    /// Execute a query and stream its rows instead of collecting them into a QueryResult
+    pub fn query_iter(&mut self, query: &str) -> Result<RowCursor<'_>, Error> {
+        self.connection.query_iter(query, &[])
+    }
+
+    pub fn query_iter_with_params(
+        &mut self,
+        query: &str,
+        params: &[Value],
+    ) -> Result<RowCursor<'_>, Error> {
+        self.connection.query_iter(query, params)
+    }
+
+    /// Prepare a query once so that it can be executed many times with different parameters
+    pub fn prepare(&mut self, query: &str) -> Result<PreparedStatement<'_>, Error> {
+        Ok(PreparedStatement {
+            statement: self.connection.prepare(query)?,
+        })
+    }
+
+    /// Begin a transaction which is rolled back when dropped without being committed
+    pub fn transaction(&mut self) -> Result<Transaction<'_>, Error> {
+        Ok(Transaction {
+            transaction: self.connection.transaction()?,
+        })
+    }
+
+    /// Execute the `;` separated statements of a script one after the other, stopping at the
+    /// first one which fails
+    pub fn execute_script(&mut self, script: &str) -> Result<Vec<QueryResult>, Error> {
+        execute_script(self.connection.as_mut(), script)
+    }
+
+    /// Execute a script in a transaction, which is rolled back if any statement fails
+    pub fn execute_script_in_transaction(
+        &mut self,
+        script: &str,
+    ) -> Result<Vec<QueryResult>, Error> {
+        let mut transaction = self.transaction()?;
+        let results = transaction.execute_script(script)?;
+        transaction.commit()?;
+        Ok(results)
+    }
+
+    /// Check that the connection is still usable
+    pub fn ping(&mut self) -> Result<(), Error> {
+        self.connection.ping()
+    }
+}
+
+fn open_connection(options: &ConnectOptions) -> Result<Box<dyn Connection + Send>, Error> {
+    options.validate()?;
+    match options {
+        #[cfg(feature = "mysql")]
+        ConnectOptions::MySql(options) => mysql::MySQLConnection::get_connection(options),
+        #[cfg(feature = "postgres")]
+        ConnectOptions::Postgres(options) => postgres::PostgresConnection::get_connection(options),
+        #[cfg(feature = "sqlite")]
+        ConnectOptions::Sqlite(options) => sqlite::SQLiteConnection::get_connection(options),
+        #[allow(unreachable_patterns)]
+        _ => Err(driver_not_compiled_in(options)),
+    }
+}
+
+/// Each backend is behind the cargo feature of the same name
+fn driver_not_compiled_in(options: &ConnectOptions) -> Error {
+    let feature = match options {
+        ConnectOptions::MySql(_) => "mysql",
+        ConnectOptions::Postgres(_) => "postgres",
+        ConnectOptions::Sqlite(_) => "sqlite",
+    };
+    Error::Configuration(format!(
+        "The {} driver is not compiled in, enable the `{}` feature of rdbc2",
+        feature, feature
+    ))
+}
+
+fn execute_script<C: Connection + ?Sized>(
+    connection: &mut C,
+    script: &str,
+) -> Result<Vec<QueryResult>, Error> {
+    sql::split_statements(connection.dialect(), script)?
+        .into_iter()
+        .map(|statement| connection.execute(statement))
+        .collect()
+}
+
+/// A transaction which is rolled back when dropped without being committed
+pub struct Transaction<'a> {
+    transaction: Box<dyn TransactionConnection + 'a>,
+}
+
+impl Transaction<'_> {
+    pub fn execute_query(&mut self, query: &str) -> Result<QueryResult, Error> {
+        self.transaction.execute(query)
+    }
+
+    pub fn execute_query_with_params(
+        &mut self,
+        query: &str,
+        params: &[Value],
+    ) -> Result<QueryResult, Error> {
+        self.transaction.execute_with_params(query, params)
+    }
+
+    pub fn execute_query_with_named_params(
+        &mut self,
+        query: &str,
+        params: &[(&str, Value)],
     ) -> Result<QueryResult, Error> {
-        let mut query = query.to_string();
-        for param in params {
-            let quoted_param = if param.strip_prefix('\'').is_some() {
-                // If the parameter already has single quotes, don't add them again.
-                // This avoids SQL injection vulnerabilities when the parameter contains a quote.
-                param.to_string()
-            } else {
-                // If the parameter doesn't have single quotes, add them.
-                format!("'{}'", param)
-            };
-            query = query.replacen('?', quoted_param.as_str(), 1);
+        let (query, params) = sql::bind_named_params(self.transaction.dialect(), query, params)?;
+        self.transaction.execute_with_params(&query, &params)
+    }
+
+    pub fn query_iter(&mut self, query: &str) -> Result<RowCursor<'_>, Error> {
+        self.transaction.query_iter(query, &[])
+    }
+
+    pub fn execute_script(&mut self, script: &str) -> Result<Vec<QueryResult>, Error> {
+        execute_script(self.transaction.as_mut(), script)
+    }
+
+    pub fn query_iter_with_params(
+        &mut self,
+        query: &str,
+        params: &[Value],
+    ) -> Result<RowCursor<'_>, Error> {
+        self.transaction.query_iter(query, params)
+    }
+
+    pub fn prepare(&mut self, query: &str) -> Result<PreparedStatement<'_>, Error> {
+        Ok(PreparedStatement {
+            statement: self.transaction.prepare(query)?,
+        })
+    }
+
+    /// Start a savepoint, a nested transaction which is released when committed and rolled
+    /// back to when rolled back or dropped, leaving the enclosing transaction active
+    pub fn savepoint(&mut self, name: &str) -> Result<Transaction<'_>, Error> {
+        let is_identifier = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_identifier {
+            return Err(Error::Usage(format!("Invalid savepoint name {}", name)));
         }
-        self.execute_query(&query)
+
+        Ok(Transaction {
+            transaction: self.transaction.savepoint(name)?,
+        })
+    }
+
+    pub fn commit(self) -> Result<(), Error> {
+        self.transaction.commit()
+    }
+
+    pub fn rollback(self) -> Result<(), Error> {
+        self.transaction.rollback()
+    }
+}
+
+pub struct PreparedStatement<'a> {
+    statement: Box<dyn Statement + 'a>,
+}
+
+impl PreparedStatement<'_> {
+    pub fn execute(&mut self, params: &[Value]) -> Result<QueryResult, Error> {
+        self.statement.execute(params)
+    }
+
+    /// Execute the statement and stream its rows as they are read from the database
+    pub fn query(&mut self, params: &[Value]) -> Result<RowCursor<'_>, Error> {
+        self.statement.query(params)
+    }
+
+    pub fn parameter_count(&self) -> usize {
+        self.statement.parameter_count()
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        self.statement.columns()
     }
 }
 
@@ -68,6 +348,125 @@ pub enum Value {
     Time(bool, u32, u8, u8, u8, u32),
 }
 
+impl Value {
+    /// Text read from a database, kept as bytes when it is not valid UTF-8
+    #[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+    pub(crate) fn from_text(text: Vec<u8>) -> Value {
+        match String::from_utf8(text) {
+            Ok(string) => Value::String(string),
+            Err(err) => Value::Bytes(err.into_bytes()),
+        }
+    }
+
+    /// The textual SQL representation of the value, `None` for NULL
+    #[cfg(any(feature = "postgres", feature = "sqlite"))]
+    pub(crate) fn to_sql_text(&self) -> Option<String> {
+        let text = match self {
+            Value::NULL => return None,
+            Value::Bytes(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+            Value::String(string) => string.clone(),
+            Value::Bool(bool) => bool.to_string(),
+            Value::Int(int) => int.to_string(),
+            Value::UInt(uint) => uint.to_string(),
+            Value::Float(float) if float.is_infinite() => infinity_text(float.is_sign_negative()),
+            Value::Double(double) if double.is_infinite() => {
+                infinity_text(double.is_sign_negative())
+            }
+            Value::Float(float) => float.to_string(),
+            Value::Double(double) => double.to_string(),
+            Value::Date(year, month, day, hour, minutes, seconds, micro_seconds) => format!(
+                "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:06}",
+                year, month, day, hour, minutes, seconds, micro_seconds
+            ),
+            Value::Time(is_negative, days, hours, minutes, seconds, micro_seconds) => format!(
+                "{}{:02}:{:02}:{:02}.{:06}",
+                if *is_negative { "-" } else { "" },
+                *days as u64 * 24 + *hours as u64,
+                minutes,
+                seconds,
+                micro_seconds
+            ),
+        };
+        Some(text)
+    }
+    /// The ISO 8601 text of a date or time, leaving out the time of day of DATE columns
+    pub(crate) fn to_iso_text(&self, column_type: &ColumnType) -> Option<String> {
+        let fraction = |micro_seconds: u32| match micro_seconds {
+            0 => String::new(),
+            micro_seconds => format!(".{:06}", micro_seconds),
+        };
+        let text = match self {
+            Value::Date(year, month, day, ..) if matches!(column_type, ColumnType::DATE) => {
+                format!("{:04}-{:02}-{:02}", year, month, day)
+            }
+            Value::Date(year, month, day, hour, minutes, seconds, micro_seconds) => format!(
+                "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}",
+                year,
+                month,
+                day,
+                hour,
+                minutes,
+                seconds,
+                fraction(*micro_seconds)
+            ),
+            Value::Time(is_negative, days, hours, minutes, seconds, micro_seconds) => format!(
+                "{}{:02}:{:02}:{:02}{}",
+                if *is_negative { "-" } else { "" },
+                *days as u64 * 24 + *hours as u64,
+                minutes,
+                seconds,
+                fraction(*micro_seconds)
+            ),
+            _ => return None,
+        };
+        Some(text)
+    }
+}
+
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+fn infinity_text(is_negative: bool) -> String {
+    if is_negative { "-Infinity" } else { "Infinity" }.to_string()
+}
+
+macro_rules! impl_from_for_value {
+    ($($t:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$t> for Value {
+                fn from(value: $t) -> Self {
+                    Value::$variant(value.into())
+                }
+            }
+        )*
+    };
+}
+
+impl_from_for_value!(
+    i8 => Int,
+    i16 => Int,
+    i32 => Int,
+    i64 => Int,
+    u8 => UInt,
+    u16 => UInt,
+    u32 => UInt,
+    u64 => UInt,
+    f32 => Float,
+    f64 => Double,
+    bool => Bool,
+    String => String,
+    &str => String,
+    Vec<u8> => Bytes,
+    &[u8] => Bytes,
+);
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        match value {
+            Some(value) => value.into(),
+            None => Value::NULL,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Column {
     pub name: String,
@@ -103,12 +502,127 @@ impl Row {
     pub fn get_column_by_name(&self, name: &str) -> Option<&Column> {
         self.columns.iter().find(|column| column.name == name)
     }
+
+    /// Read a value as a Rust type, by the position or the name of its column
+    pub fn get<T: FromValue>(&self, index: impl ColumnIndex) -> Result<T, Error> {
+        let Some((column, value)) = index
+            .position(&self.columns)
+            .and_then(|position| Some((&self.columns[position], self.values.get(position)?)))
+        else {
+            return Err(Error::Usage(format!("No column {} in the row", index)));
+        };
+        T::from_value(value).map_err(|err| from_value::column_error(column, err))
+    }
+
+    /// Deserialize the row as a map from column names to values, e.g. into a struct deriving
+    /// `serde::Deserialize`
+    pub fn deserialize<'de, T: Deserialize<'de>>(&'de self) -> Result<T, Error> {
+        T::deserialize(deserialize::RowDeserializer::new(self))
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct QueryResult {
     pub rows: Vec<Row>,
+    /// The columns of the rows, also known when no row is returned
+    #[serde(default)]
+    pub columns: Arc<[Column]>,
     pub affected_row_count: usize,
+    #[serde(default)]
+    pub kind: StatementKind,
+}
+
+impl QueryResult {
+    /// Deserialize each row as a map from column names to values, see `Row::deserialize`
+    pub fn deserialize_rows<'de, T: Deserialize<'de>>(&'de self) -> Result<Vec<T>, Error> {
+        self.rows.iter().map(Row::deserialize).collect()
+    }
+
+    /// Tag a result with the kind of statement which produced it. Data modifying statements
+    /// which return rows, e.g. with RETURNING, report one affected row per returned row.
+    #[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+    pub(crate) fn with_kind(mut self, kind: StatementKind) -> Self {
+        if kind.modifies_data() && self.affected_row_count == 0 {
+            self.affected_row_count = self.rows.len();
+        }
+        self.kind = kind;
+        self
+    }
+}
+
+/// Collect all the rows of a cursor
+impl TryFrom<RowCursor<'_>> for QueryResult {
+    type Error = Error;
+
+    fn try_from(mut cursor: RowCursor<'_>) -> Result<Self, Error> {
+        let rows = cursor.by_ref().collect::<Result<Vec<Row>, Error>>()?;
+        // Rows can resolve the types of columns which are unknown before reading them
+        let columns = rows.last().map_or_else(
+            || Arc::clone(&cursor.columns),
+            |row| Arc::clone(&row.columns),
+        );
+        Ok(QueryResult {
+            rows,
+            columns,
+            affected_row_count: 0,
+            kind: StatementKind::Query,
+        })
+    }
+}
+
+/// Rows of a query, read from the database one at a time as the cursor is iterated
+pub struct RowCursor<'a> {
+    columns: Arc<[Column]>,
+    rows: Box<dyn Iterator<Item = Result<Row, Error>> + 'a>,
+}
+
+impl<'a> RowCursor<'a> {
+    pub fn new(
+        columns: Arc<[Column]>,
+        rows: impl Iterator<Item = Result<Row, Error>> + 'a,
+    ) -> Self {
+        RowCursor {
+            columns,
+            rows: Box::new(rows),
+        }
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+}
+
+impl Iterator for RowCursor<'_> {
+    type Item = Result<Row, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rows.next()
+    }
+}
+
+/// The kind of a statement, decided by its leading keyword
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StatementKind {
+    /// SELECT, VALUES, SHOW, EXPLAIN, PRAGMA... statements reading data
+    Query,
+    Insert,
+    Update,
+    Delete,
+    /// CREATE, ALTER, DROP... statements changing the schema
+    Ddl,
+    /// BEGIN, COMMIT, ROLLBACK, SAVEPOINT... statements controlling transactions
+    Transaction,
+    #[default]
+    Other,
+}
+
+impl StatementKind {
+    pub fn modifies_data(&self) -> bool {
+        matches!(
+            self,
+            StatementKind::Insert | StatementKind::Update | StatementKind::Delete
+        )
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -0,0 +1,135 @@
+use std::future::Future;
+use std::sync::mpsc;
+
+use tokio::sync::oneshot;
+
+use crate::dbc::{Database, DatabaseError, Error, QueryResult, Value};
+
+pub trait AsyncConnection {
+    fn execute(&self, query: &str) -> impl Future<Output = Result<QueryResult, Error>> + Send;
+    /// Execute a query with `?` placeholders bound to the given parameters by the database
+    fn execute_with_params(
+        &self,
+        query: &str,
+        params: &[Value],
+    ) -> impl Future<Output = Result<QueryResult, Error>> + Send;
+}
+
+type Job = Box<dyn FnOnce(&mut Database) + Send>;
+
+/// A database connection which can be used from async code without blocking the runtime.
+/// The connection lives on a dedicated thread running queries one at a time in the order they
+/// were submitted, until the AsyncDatabase is dropped.
+pub struct AsyncDatabase {
+    jobs: mpsc::Sender<Job>,
+}
+
+impl AsyncDatabase {
+    pub async fn new(url: &str) -> Result<Self, Error> {
+        let url = url.to_owned();
+        let (opened, is_opened) = oneshot::channel();
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        // Connect on the connection thread too, some drivers must not block inside a runtime
+        std::thread::spawn(move || match Database::new(&url) {
+            Ok(mut database) => {
+                let _ = opened.send(Ok(()));
+                serve(&mut database, receiver);
+            }
+            Err(err) => {
+                let _ = opened.send(Err(err));
+            }
+        });
+        is_opened.await.map_err(|_| connection_closed())??;
+
+        Ok(AsyncDatabase { jobs })
+    }
+
+    pub async fn execute_query(&self, query: &str) -> Result<QueryResult, Error> {
+        let query = query.to_owned();
+        self.run(move |database| database.execute_query(&query))
+            .await
+    }
+
+    pub async fn execute_query_with_params(
+        &self,
+        query: &str,
+        params: &[Value],
+    ) -> Result<QueryResult, Error> {
+        let query = query.to_owned();
+        let params = params.to_vec();
+        self.run(move |database| database.execute_query_with_params(&query, &params))
+            .await
+    }
+
+    /// Execute a query with `:name`, `$name` or `@name` placeholders bound to the given parameters
+    pub async fn execute_query_with_named_params(
+        &self,
+        query: &str,
+        params: &[(&str, Value)],
+    ) -> Result<QueryResult, Error> {
+        let query = query.to_owned();
+        let params = params
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect::<Vec<(String, Value)>>();
+        self.run(move |database| {
+            let params = params
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.clone()))
+                .collect::<Vec<(&str, Value)>>();
+            database.execute_query_with_named_params(&query, &params)
+        })
+        .await
+    }
+
+    /// Run a closure with exclusive access to the blocking Database on the connection thread,
+    /// e.g. to use transactions, prepared statements or cursors
+    pub async fn run<T, F>(&self, f: F) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Database) -> Result<T, Error> + Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        self.jobs
+            .send(Box::new(move |database| {
+                let _ = sender.send(f(database));
+            }))
+            .map_err(|_| connection_closed())?;
+        receiver.await.map_err(|_| connection_closed())?
+    }
+}
+
+/// Move a connection onto its own thread
+impl From<Database> for AsyncDatabase {
+    fn from(mut database: Database) -> Self {
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        std::thread::spawn(move || serve(&mut database, receiver));
+        AsyncDatabase { jobs }
+    }
+}
+
+impl AsyncConnection for AsyncDatabase {
+    async fn execute(&self, query: &str) -> Result<QueryResult, Error> {
+        self.execute_query(query).await
+    }
+
+    async fn execute_with_params(
+        &self,
+        query: &str,
+        params: &[Value],
+    ) -> Result<QueryResult, Error> {
+        self.execute_query_with_params(query, params).await
+    }
+}
+
+fn serve(database: &mut Database, jobs: mpsc::Receiver<Job>) {
+    for job in jobs {
+        job(database);
+    }
+}
+
+/// The connection thread stops once the AsyncDatabase is dropped or a closure passed to `run`
+/// panics
+fn connection_closed() -> Error {
+    Error::Connection(DatabaseError::new("The connection thread has stopped"))
+}
@@ -0,0 +1,432 @@
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+use crate::dbc::{
+    Column, ColumnType, DatabaseError, Error, QueryResult, Row, StatementKind, Value,
+};
+
+const MAGIC: &[u8; 4] = b"RDBC";
+/// The version of the encoding, increased on any incompatible change
+const VERSION: u8 = 1;
+
+const ROW: u8 = 1;
+const END: u8 = 0;
+
+/// Writes rows in the binary format described in the README as they come, e.g. from a
+/// RowCursor, without collecting them into a QueryResult first
+pub struct Encoder<W: Write> {
+    writer: W,
+    column_count: usize,
+}
+
+impl<W: Write> Encoder<W> {
+    /// Start a result by writing the header with its columns
+    pub fn new(mut writer: W, columns: &[Column]) -> Result<Self, Error> {
+        write_header(&mut writer, columns)?;
+        Ok(Encoder {
+            writer,
+            column_count: columns.len(),
+        })
+    }
+
+    /// Write a row, which must have one value per column of the header
+    pub fn encode_row(&mut self, row: &Row) -> Result<(), Error> {
+        if row.values.len() != self.column_count {
+            return Err(Error::Usage(format!(
+                "Cannot encode a row of {} values with {} columns",
+                row.values.len(),
+                self.column_count
+            )));
+        }
+        self.writer.write_all(&[ROW]).map_err(io_error)?;
+        row.values
+            .iter()
+            .try_for_each(|value| write_value(&mut self.writer, value))
+    }
+
+    /// End the result after its rows, returning the flushed writer
+    pub fn finish(mut self, affected_row_count: usize, kind: StatementKind) -> Result<W, Error> {
+        self.writer.write_all(&[END]).map_err(io_error)?;
+        write_varint(&mut self.writer, affected_row_count as u64)?;
+        self.writer
+            .write_all(&[statement_kind_tag(kind)])
+            .map_err(io_error)?;
+        self.writer.flush().map_err(io_error)?;
+        Ok(self.writer)
+    }
+}
+
+/// Reads the rows of a result written by an Encoder one at a time, as an iterator
+pub struct Decoder<R: Read> {
+    reader: R,
+    columns: Arc<[Column]>,
+    end: Option<(usize, StatementKind)>,
+}
+
+impl<R: Read> Decoder<R> {
+    /// Start reading a result by reading the header with its columns
+    pub fn new(mut reader: R) -> Result<Self, Error> {
+        let columns = read_header(&mut reader)?;
+        Ok(Decoder {
+            reader,
+            columns,
+            end: None,
+        })
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// The next row, or `None` once all the rows have been read
+    pub fn decode_row(&mut self) -> Result<Option<Row>, Error> {
+        if self.end.is_some() {
+            return Ok(None);
+        }
+        match read_array(&mut self.reader)? {
+            [ROW] => {
+                let values = self
+                    .columns
+                    .iter()
+                    .map(|_| read_value(&mut self.reader))
+                    .collect::<Result<Vec<Value>, Error>>()?;
+                Ok(Some(Row::new(values, self.columns.clone())))
+            }
+            [END] => {
+                let affected_row_count = read_length(&mut self.reader)?;
+                let [tag] = read_array(&mut self.reader)?;
+                self.end = Some((affected_row_count, statement_kind(tag)?));
+                Ok(None)
+            }
+            [marker] => Err(invalid(&format!("unknown row marker {}", marker))),
+        }
+    }
+
+    /// The affected row count of the result, known once all the rows have been read
+    pub fn affected_row_count(&self) -> Option<usize> {
+        self.end.map(|(affected_row_count, _)| affected_row_count)
+    }
+
+    /// The kind of statement which produced the result, known once all the rows have been read
+    pub fn kind(&self) -> Option<StatementKind> {
+        self.end.map(|(_, kind)| kind)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> Iterator for Decoder<R> {
+    type Item = Result<Row, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.decode_row().transpose()
+    }
+}
+
+impl QueryResult {
+    /// Encode the result in the versioned binary format described in the README, which
+    /// `from_bytes` decodes back on another machine without losing any type
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut encoder = Encoder::new(Vec::new(), &self.columns)?;
+        for row in &self.rows {
+            encoder.encode_row(row)?;
+        }
+        encoder.finish(self.affected_row_count, self.kind)
+    }
+
+    /// Decode a result encoded with `to_bytes` or an Encoder
+    pub fn from_bytes(bytes: &[u8]) -> Result<QueryResult, Error> {
+        let mut decoder = Decoder::new(bytes)?;
+        let rows = decoder.by_ref().collect::<Result<Vec<Row>, Error>>()?;
+        let (affected_row_count, kind) = decoder.end.expect("the rows are read until the end");
+        let columns = Arc::clone(&decoder.columns);
+        if !decoder.into_inner().is_empty() {
+            return Err(invalid("trailing bytes after the end of the result"));
+        }
+        Ok(QueryResult {
+            rows,
+            columns,
+            affected_row_count,
+            kind,
+        })
+    }
+}
+
+fn write_header(writer: &mut impl Write, columns: &[Column]) -> Result<(), Error> {
+    writer.write_all(MAGIC).map_err(io_error)?;
+    writer.write_all(&[VERSION]).map_err(io_error)?;
+    write_varint(writer, columns.len() as u64)?;
+    for column in columns {
+        write_bytes(writer, column.name.as_bytes())?;
+        writer
+            .write_all(&[column_type_tag(&column.column_type)])
+            .map_err(io_error)?;
+    }
+    Ok(())
+}
+
+fn write_value(writer: &mut impl Write, value: &Value) -> Result<(), Error> {
+    match value {
+        Value::NULL => writer.write_all(&[0]).map_err(io_error),
+        Value::Bytes(bytes) => {
+            writer.write_all(&[1]).map_err(io_error)?;
+            write_bytes(writer, bytes)
+        }
+        Value::String(string) => {
+            writer.write_all(&[2]).map_err(io_error)?;
+            write_bytes(writer, string.as_bytes())
+        }
+        Value::Bool(bool) => writer.write_all(&[3, *bool as u8]).map_err(io_error),
+        Value::Int(int) => {
+            writer.write_all(&[4]).map_err(io_error)?;
+            write_varint(writer, ((int << 1) ^ (int >> 63)) as u64)
+        }
+        Value::UInt(uint) => {
+            writer.write_all(&[5]).map_err(io_error)?;
+            write_varint(writer, *uint)
+        }
+        Value::Float(float) => {
+            writer.write_all(&[6]).map_err(io_error)?;
+            writer.write_all(&float.to_le_bytes()).map_err(io_error)
+        }
+        Value::Double(double) => {
+            writer.write_all(&[7]).map_err(io_error)?;
+            writer.write_all(&double.to_le_bytes()).map_err(io_error)
+        }
+        Value::Date(year, month, day, hour, minutes, seconds, micro_seconds) => {
+            let mut bytes = vec![8];
+            bytes.extend_from_slice(&year.to_le_bytes());
+            bytes.extend_from_slice(&[*month, *day, *hour, *minutes, *seconds]);
+            bytes.extend_from_slice(&micro_seconds.to_le_bytes());
+            writer.write_all(&bytes).map_err(io_error)
+        }
+        Value::Time(is_negative, days, hours, minutes, seconds, micro_seconds) => {
+            let mut bytes = vec![9, *is_negative as u8];
+            bytes.extend_from_slice(&days.to_le_bytes());
+            bytes.extend_from_slice(&[*hours, *minutes, *seconds]);
+            bytes.extend_from_slice(&micro_seconds.to_le_bytes());
+            writer.write_all(&bytes).map_err(io_error)
+        }
+    }
+}
+
+fn write_bytes(writer: &mut impl Write, bytes: &[u8]) -> Result<(), Error> {
+    write_varint(writer, bytes.len() as u64)?;
+    writer.write_all(bytes).map_err(io_error)
+}
+
+fn write_varint(writer: &mut impl Write, mut value: u64) -> Result<(), Error> {
+    let mut bytes = Vec::with_capacity(10);
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+    writer.write_all(&bytes).map_err(io_error)
+}
+
+fn read_header(reader: &mut impl Read) -> Result<Arc<[Column]>, Error> {
+    let magic: [u8; 4] = read_array(reader)?;
+    if &magic != MAGIC {
+        return Err(invalid("not an encoded query result"));
+    }
+    let [version] = read_array(reader)?;
+    if version != VERSION {
+        return Err(invalid(&format!(
+            "unsupported version {}, expected {}",
+            version, VERSION
+        )));
+    }
+    let column_count = read_length(reader)?;
+    let mut columns = Vec::with_capacity(column_count.min(1024));
+    for _ in 0..column_count {
+        let name = read_string(reader)?;
+        let [tag] = read_array(reader)?;
+        columns.push(Column {
+            name,
+            column_type: column_type(tag)?,
+        });
+    }
+    Ok(columns.into())
+}
+
+fn read_value(reader: &mut impl Read) -> Result<Value, Error> {
+    let [tag] = read_array(reader)?;
+    let value = match tag {
+        0 => Value::NULL,
+        1 => Value::Bytes(read_bytes(reader)?),
+        2 => Value::String(read_string(reader)?),
+        3 => match read_array(reader)? {
+            [0] => Value::Bool(false),
+            [1] => Value::Bool(true),
+            [bool] => return Err(invalid(&format!("invalid boolean {}", bool))),
+        },
+        4 => {
+            let zigzag = read_varint(reader)?;
+            Value::Int((zigzag >> 1) as i64 ^ -((zigzag & 1) as i64))
+        }
+        5 => Value::UInt(read_varint(reader)?),
+        6 => Value::Float(f32::from_le_bytes(read_array(reader)?)),
+        7 => Value::Double(f64::from_le_bytes(read_array(reader)?)),
+        8 => {
+            let [year_low, year_high, month, day, hour, minutes, seconds, micro_seconds @ ..] =
+                read_array::<11>(reader)?;
+            Value::Date(
+                u16::from_le_bytes([year_low, year_high]),
+                month,
+                day,
+                hour,
+                minutes,
+                seconds,
+                u32::from_le_bytes(micro_seconds),
+            )
+        }
+        9 => {
+            let [is_negative, days @ .., hours, minutes, seconds] = read_array::<8>(reader)?;
+            let micro_seconds = u32::from_le_bytes(read_array(reader)?);
+            Value::Time(
+                is_negative != 0,
+                u32::from_le_bytes(days),
+                hours,
+                minutes,
+                seconds,
+                micro_seconds,
+            )
+        }
+        tag => return Err(invalid(&format!("unknown value tag {}", tag))),
+    };
+    Ok(value)
+}
+
+fn read_string(reader: &mut impl Read) -> Result<String, Error> {
+    String::from_utf8(read_bytes(reader)?).map_err(|_| invalid("invalid UTF-8 in a string"))
+}
+
+fn read_bytes(reader: &mut impl Read) -> Result<Vec<u8>, Error> {
+    let length = read_length(reader)?;
+    let mut bytes = Vec::new();
+    reader
+        .take(length as u64)
+        .read_to_end(&mut bytes)
+        .map_err(io_error)?;
+    if bytes.len() != length {
+        return Err(invalid("unexpected end of input"));
+    }
+    Ok(bytes)
+}
+
+fn read_length(reader: &mut impl Read) -> Result<usize, Error> {
+    usize::try_from(read_varint(reader)?).map_err(|_| invalid("length out of range"))
+}
+
+fn read_varint(reader: &mut impl Read) -> Result<u64, Error> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let [byte] = read_array(reader)?;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid("varint longer than 64 bits"))
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> Result<[u8; N], Error> {
+    let mut bytes = [0; N];
+    reader
+        .read_exact(&mut bytes)
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::UnexpectedEof => invalid("unexpected end of input"),
+            _ => io_error(err),
+        })?;
+    Ok(bytes)
+}
+
+fn column_type_tag(column_type: &ColumnType) -> u8 {
+    match column_type {
+        ColumnType::NULL => 0,
+        ColumnType::DECIMAL => 1,
+        ColumnType::INT => 2,
+        ColumnType::FLOAT => 3,
+        ColumnType::BIT => 4,
+        ColumnType::DOUBLE => 5,
+        ColumnType::STRING => 6,
+        ColumnType::VARCHAR => 7,
+        ColumnType::TIMESTAMP => 8,
+        ColumnType::DATE => 9,
+        ColumnType::TIME => 10,
+        ColumnType::YEAR => 11,
+        ColumnType::DATETIME => 12,
+        ColumnType::JSON => 13,
+        ColumnType::ENUM => 14,
+        ColumnType::SET => 15,
+        ColumnType::BLOB => 16,
+        ColumnType::GEOMETRY => 17,
+        ColumnType::UNKNOWN => 18,
+    }
+}
+
+fn column_type(tag: u8) -> Result<ColumnType, Error> {
+    let column_type = match tag {
+        0 => ColumnType::NULL,
+        1 => ColumnType::DECIMAL,
+        2 => ColumnType::INT,
+        3 => ColumnType::FLOAT,
+        4 => ColumnType::BIT,
+        5 => ColumnType::DOUBLE,
+        6 => ColumnType::STRING,
+        7 => ColumnType::VARCHAR,
+        8 => ColumnType::TIMESTAMP,
+        9 => ColumnType::DATE,
+        10 => ColumnType::TIME,
+        11 => ColumnType::YEAR,
+        12 => ColumnType::DATETIME,
+        13 => ColumnType::JSON,
+        14 => ColumnType::ENUM,
+        15 => ColumnType::SET,
+        16 => ColumnType::BLOB,
+        17 => ColumnType::GEOMETRY,
+        18 => ColumnType::UNKNOWN,
+        tag => return Err(invalid(&format!("unknown column type {}", tag))),
+    };
+    Ok(column_type)
+}
+
+fn statement_kind_tag(kind: StatementKind) -> u8 {
+    match kind {
+        StatementKind::Query => 0,
+        StatementKind::Insert => 1,
+        StatementKind::Update => 2,
+        StatementKind::Delete => 3,
+        StatementKind::Ddl => 4,
+        StatementKind::Transaction => 5,
+        StatementKind::Other => 6,
+    }
+}
+
+fn statement_kind(tag: u8) -> Result<StatementKind, Error> {
+    let kind = match tag {
+        0 => StatementKind::Query,
+        1 => StatementKind::Insert,
+        2 => StatementKind::Update,
+        3 => StatementKind::Delete,
+        4 => StatementKind::Ddl,
+        5 => StatementKind::Transaction,
+        6 => StatementKind::Other,
+        tag => return Err(invalid(&format!("unknown statement kind {}", tag))),
+    };
+    Ok(kind)
+}
+
+fn invalid(message: &str) -> Error {
+    Error::TypeConversion(DatabaseError::new(format!(
+        "Invalid binary query result: {}",
+        message
+    )))
+}
+
+fn io_error(err: io::Error) -> Error {
+    Error::Database(DatabaseError::from_source(err))
+}
@@ -0,0 +1,257 @@
+use std::fmt;
+
+use serde::de::value::BorrowedStrDeserializer;
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::forward_to_deserialize_any;
+
+use crate::dbc::{from_value, Column, DatabaseError, Error, Row, Value};
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::TypeConversion(DatabaseError::new(msg.to_string()))
+    }
+}
+
+/// Deserializes a row as a map from column names to values, or as a sequence of values
+pub(crate) struct RowDeserializer<'a> {
+    row: &'a Row,
+}
+
+impl<'a> RowDeserializer<'a> {
+    pub(crate) fn new(row: &'a Row) -> Self {
+        RowDeserializer { row }
+    }
+}
+
+impl<'de> Deserializer<'de> for RowDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(RowAccess::new(self.row))
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(RowAccess::new(self.row))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct map struct enum identifier ignored_any
+    }
+}
+
+/// Walks the columns of a row, as map entries or as sequence elements
+struct RowAccess<'a> {
+    columns: std::iter::Zip<std::slice::Iter<'a, Column>, std::slice::Iter<'a, Value>>,
+    value: Option<(&'a Column, &'a Value)>,
+}
+
+impl<'a> RowAccess<'a> {
+    fn new(row: &'a Row) -> Self {
+        RowAccess {
+            columns: row.columns.iter().zip(row.values.iter()),
+            value: None,
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for RowAccess<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let Some((column, value)) = self.columns.next() else {
+            return Ok(None);
+        };
+        self.value = Some((column, value));
+        seed.deserialize(BorrowedStrDeserializer::new(&column.name))
+            .map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (column, value) = self
+            .value
+            .take()
+            .ok_or_else(|| Error::Usage("Value requested before its column".to_owned()))?;
+        seed.deserialize(ValueDeserializer { column, value })
+            .map_err(|err| from_value::column_error(column, err))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.columns.len())
+    }
+}
+
+impl<'de> SeqAccess<'de> for RowAccess<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        let Some((column, value)) = self.columns.next() else {
+            return Ok(None);
+        };
+        seed.deserialize(ValueDeserializer { column, value })
+            .map(Some)
+            .map_err(|err| from_value::column_error(column, err))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.columns.len())
+    }
+}
+
+/// Deserializes a value as the closest serde type. Dates and times are given as ISO 8601 text,
+/// which the chrono and time crates deserialize from.
+struct ValueDeserializer<'a> {
+    column: &'a Column,
+    value: &'a Value,
+}
+
+impl ValueDeserializer<'_> {
+    fn text(&self) -> Option<&str> {
+        match self.value {
+            Value::String(string) => Some(string),
+            Value::Bytes(bytes) => std::str::from_utf8(bytes).ok(),
+            _ => None,
+        }
+    }
+}
+
+// Numbers are also parsed from text, e.g. PostgreSQL NUMERIC values
+macro_rules! deserialize_number {
+    ($($method:ident => $number:ty, $visit:ident;)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match self.text().and_then(|text| text.parse::<$number>().ok()) {
+                    Some(number) => visitor.$visit(number),
+                    None => self.deserialize_any(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::NULL => visitor.visit_none(),
+            Value::Bytes(bytes) => visitor.visit_borrowed_bytes(bytes),
+            Value::String(string) => visitor.visit_borrowed_str(string),
+            Value::Bool(bool) => visitor.visit_bool(*bool),
+            Value::Int(int) => visitor.visit_i64(*int),
+            Value::UInt(uint) => visitor.visit_u64(*uint),
+            Value::Float(float) => visitor.visit_f32(*float),
+            Value::Double(double) => visitor.visit_f64(*double),
+            value => visitor.visit_string(
+                value
+                    .to_iso_text(&self.column.column_type)
+                    .expect("dates and times have an ISO 8601 text"),
+            ),
+        }
+    }
+
+    /// Booleans are stored as 0 or 1 by databases without a boolean type
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Int(0) | Value::UInt(0) => visitor.visit_bool(false),
+            Value::Int(1) | Value::UInt(1) => visitor.visit_bool(true),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    deserialize_number! {
+        deserialize_i8 => i8, visit_i8;
+        deserialize_i16 => i16, visit_i16;
+        deserialize_i32 => i32, visit_i32;
+        deserialize_i64 => i64, visit_i64;
+        deserialize_i128 => i128, visit_i128;
+        deserialize_u8 => u8, visit_u8;
+        deserialize_u16 => u16, visit_u16;
+        deserialize_u32 => u32, visit_u32;
+        deserialize_u64 => u64, visit_u64;
+        deserialize_u128 => u128, visit_u128;
+        deserialize_f32 => f32, visit_f32;
+        deserialize_f64 => f64, visit_f64;
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::String(string) => visitor.visit_borrowed_bytes(string.as_bytes()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::NULL => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::NULL => visitor.visit_unit(),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Unit variants are read from text, e.g. the values of a MySQL ENUM column
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.value {
+            Value::String(string) => visitor.visit_enum(BorrowedStrDeserializer::new(string)),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        char str string unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use crate::dbc::{self, ConnectOptions, Connection, Database, Error};
+
+/// Opens connections for the URLs with a given scheme, e.g. `mysql` for `mysql://...` URLs
+pub trait Driver: Send + Sync {
+    fn scheme(&self) -> &str;
+    fn connect(&self, url: &str) -> Result<Box<dyn Connection + Send>, Error>;
+}
+
+/// The drivers used to resolve database URLs by their scheme
+#[derive(Clone, Default)]
+pub struct DriverRegistry {
+    drivers: HashMap<String, Arc<dyn Driver>>,
+}
+
+impl DriverRegistry {
+    /// An empty registry without any driver
+    pub fn new() -> Self {
+        DriverRegistry::default()
+    }
+
+    /// A registry with the built-in MySQL, PostgreSQL and SQLite drivers
+    pub fn builtin() -> Self {
+        let mut registry = DriverRegistry::new();
+        for scheme in ["mysql", "postgres", "postgresql", "sqlite"] {
+            registry.register(BuiltinDriver { scheme });
+        }
+        registry
+    }
+
+    /// The registry used by `Database::new`, which starts with the built-in drivers
+    pub fn global() -> &'static RwLock<DriverRegistry> {
+        static GLOBAL: OnceLock<RwLock<DriverRegistry>> = OnceLock::new();
+        GLOBAL.get_or_init(|| RwLock::new(DriverRegistry::builtin()))
+    }
+
+    /// Register a driver for its scheme, replacing the driver previously registered for it
+    pub fn register(&mut self, driver: impl Driver + 'static) {
+        self.drivers
+            .insert(driver.scheme().to_owned(), Arc::new(driver));
+    }
+
+    pub fn driver(&self, scheme: &str) -> Option<Arc<dyn Driver>> {
+        self.drivers.get(scheme).cloned()
+    }
+
+    /// Connect with the driver registered for the scheme of the URL
+    pub fn connect(&self, url: &str) -> Result<Database, Error> {
+        connect(self.resolve(url)?, url)
+    }
+
+    /// The driver registered for the scheme of the URL
+    pub(crate) fn resolve(&self, url: &str) -> Result<Arc<dyn Driver>, Error> {
+        let Some((scheme, _)) = url.split_once("://") else {
+            return Err(Error::Configuration(format!(
+                "Missing scheme in database URL {}",
+                url
+            )));
+        };
+        self.driver(scheme)
+            .ok_or_else(|| Error::UnsupportedScheme(scheme.to_owned()))
+    }
+}
+
+pub(crate) fn connect(driver: Arc<dyn Driver>, url: &str) -> Result<Database, Error> {
+    Ok(Database {
+        connection: driver.connect(url)?,
+    })
+}
+
+/// Register a driver in the global registry, so that `Database::new` resolves its URLs
+pub fn register_driver(driver: impl Driver + 'static) {
+    DriverRegistry::global()
+        .write()
+        .unwrap_or_else(|err| err.into_inner())
+        .register(driver);
+}
+
+struct BuiltinDriver {
+    scheme: &'static str,
+}
+
+impl Driver for BuiltinDriver {
+    fn scheme(&self) -> &str {
+        self.scheme
+    }
+
+    fn connect(&self, url: &str) -> Result<Box<dyn Connection + Send>, Error> {
+        dbc::open_connection(&ConnectOptions::from_url(url)?)
+    }
+}
@@ -0,0 +1,186 @@
+use std::fmt;
+
+/// The errors returned by the database API, classified across backends
+#[derive(Debug)]
+pub enum Error {
+    /// Connecting to the database failed or the connection was lost
+    Connection(DatabaseError),
+    /// The database could not parse the query
+    Syntax(DatabaseError),
+    /// The statement violated a constraint of the schema
+    ConstraintViolation {
+        kind: ConstraintKind,
+        error: DatabaseError,
+    },
+    /// The transaction was aborted to resolve a deadlock
+    Deadlock(DatabaseError),
+    /// Waiting for a lock, the database or a pooled connection took too long
+    Timeout(DatabaseError),
+    /// A value could not be converted between its database and Rust representations
+    TypeConversion(DatabaseError),
+    /// No driver is registered for the scheme of a database URL
+    UnsupportedScheme(String),
+    /// Invalid connection options or database URL
+    Configuration(String),
+    /// The API was misused, e.g. by nesting transactions or leaving out a named parameter
+    Usage(String),
+    /// Any other error reported by the database or its driver
+    Database(DatabaseError),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConstraintKind {
+    /// A unique or primary key constraint
+    Unique,
+    ForeignKey,
+    NotNull,
+    Check,
+    Other,
+}
+
+/// The details of an error reported by a database or its driver
+#[derive(Debug)]
+pub struct DatabaseError {
+    message: String,
+    sql_state: Option<String>,
+    code: Option<i32>,
+    source: Option<Box<dyn std::error::Error + Send + Sync>>,
+}
+
+impl Error {
+    /// The details of the error, if it comes from the database or its driver
+    pub fn database_error(&self) -> Option<&DatabaseError> {
+        match self {
+            Error::Connection(error)
+            | Error::Syntax(error)
+            | Error::ConstraintViolation { error, .. }
+            | Error::Deadlock(error)
+            | Error::Timeout(error)
+            | Error::TypeConversion(error)
+            | Error::Database(error) => Some(error),
+            Error::UnsupportedScheme(_) | Error::Configuration(_) | Error::Usage(_) => None,
+        }
+    }
+
+    /// The five character SQLSTATE reported by MySQL and PostgreSQL
+    pub fn sql_state(&self) -> Option<&str> {
+        self.database_error()?.sql_state()
+    }
+
+    /// The native error code reported by MySQL or the extended result code reported by SQLite
+    pub fn code(&self) -> Option<i32> {
+        self.database_error()?.code()
+    }
+
+    /// Classify an error reported with a SQLSTATE, falling back to `Database`
+    #[cfg(any(feature = "mysql", feature = "postgres"))]
+    pub(crate) fn from_sql_state(error: DatabaseError) -> Error {
+        let Some(sql_state) = error.sql_state() else {
+            return Error::Database(error);
+        };
+        let kind = match sql_state {
+            "23502" => ConstraintKind::NotNull,
+            "23503" => ConstraintKind::ForeignKey,
+            "23505" => ConstraintKind::Unique,
+            "23514" => ConstraintKind::Check,
+            _ if sql_state.starts_with("23") => ConstraintKind::Other,
+            // Connection exceptions, invalid authorization and unknown databases
+            _ if ["08", "28", "3D"]
+                .iter()
+                .any(|class| sql_state.starts_with(class)) =>
+            {
+                return Error::Connection(error)
+            }
+            "40P01" => return Error::Deadlock(error),
+            "42601" => return Error::Syntax(error),
+            "55P03" | "57014" => return Error::Timeout(error),
+            _ => return Error::Database(error),
+        };
+        Error::ConstraintViolation { kind, error }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnsupportedScheme(scheme) => {
+                write!(f, "Unsupported database URL scheme {}", scheme)
+            }
+            Error::Configuration(message) | Error::Usage(message) => f.write_str(message),
+            Error::Connection(error)
+            | Error::Syntax(error)
+            | Error::ConstraintViolation { error, .. }
+            | Error::Deadlock(error)
+            | Error::Timeout(error)
+            | Error::TypeConversion(error)
+            | Error::Database(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.database_error()?.source()
+    }
+}
+
+impl DatabaseError {
+    pub fn new(message: impl Into<String>) -> Self {
+        DatabaseError {
+            message: message.into(),
+            sql_state: None,
+            code: None,
+            source: None,
+        }
+    }
+
+    /// Wrap an error of the driver, using its message
+    pub fn from_source(source: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        let source = source.into();
+        DatabaseError::new(source.to_string()).with_source(source)
+    }
+
+    pub fn with_source(
+        mut self,
+        source: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    pub fn with_sql_state(mut self, sql_state: impl Into<String>) -> Self {
+        self.sql_state = Some(sql_state.into());
+        self
+    }
+
+    pub fn with_code(mut self, code: i32) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn sql_state(&self) -> Option<&str> {
+        self.sql_state.as_deref()
+    }
+
+    pub fn code(&self) -> Option<i32> {
+        self.code
+    }
+}
+
+impl fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for DatabaseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|source| source as &(dyn std::error::Error + 'static))
+    }
+}
@@ -0,0 +1,7 @@
+use crate::dbc::{Error, Row};
+
+/// Conversion of a row into a Rust type, used by `Database::query_as`. With the `derive`
+/// feature, it can be derived for structs reading each field from the column of the same name.
+pub trait FromRow: Sized {
+    fn from_row(row: &Row) -> Result<Self, Error>;
+}
@@ -0,0 +1,359 @@
+use std::fmt;
+
+use crate::dbc::{Column, DatabaseError, Error, Value};
+
+/// Conversion of a database value into a Rust type, used by `Row::get`
+pub trait FromValue: Sized {
+    fn from_value(value: &Value) -> Result<Self, Error>;
+}
+
+/// A column position or name to read a value of a Row from
+pub trait ColumnIndex: fmt::Display {
+    fn position(&self, columns: &[Column]) -> Option<usize>;
+}
+
+impl ColumnIndex for usize {
+    fn position(&self, columns: &[Column]) -> Option<usize> {
+        (*self < columns.len()).then_some(*self)
+    }
+}
+
+impl ColumnIndex for &str {
+    fn position(&self, columns: &[Column]) -> Option<usize> {
+        columns.iter().position(|column| column.name == *self)
+    }
+}
+
+impl FromValue for Value {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        Ok(value.clone())
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::NULL => Ok(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+// Numbers are also parsed from text, which some databases send for decimals or over their text
+// protocol
+macro_rules! impl_from_value_for_integer {
+    ($($integer:ty),*) => {
+        $(
+            impl FromValue for $integer {
+                fn from_value(value: &Value) -> Result<Self, Error> {
+                    let integer = match value {
+                        Value::Int(int) => <$integer>::try_from(*int).ok(),
+                        Value::UInt(uint) => <$integer>::try_from(*uint).ok(),
+                        value => text(value).and_then(|text| text.parse().ok()),
+                    };
+                    integer.ok_or_else(|| conversion_error::<$integer>(value))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_value_for_integer!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+macro_rules! impl_from_value_for_float {
+    ($($float:ty),*) => {
+        $(
+            impl FromValue for $float {
+                fn from_value(value: &Value) -> Result<Self, Error> {
+                    let float = match value {
+                        Value::Float(float) => Some(*float as $float),
+                        Value::Double(double) => Some(*double as $float),
+                        Value::Int(int) => Some(*int as $float),
+                        Value::UInt(uint) => Some(*uint as $float),
+                        value => text(value).and_then(|text| text.parse().ok()),
+                    };
+                    float.ok_or_else(|| conversion_error::<$float>(value))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_value_for_float!(f32, f64);
+
+/// Booleans are stored as 0 or 1 by databases without a boolean type
+impl FromValue for bool {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::Bool(bool) => Ok(*bool),
+            Value::Int(0) | Value::UInt(0) => Ok(false),
+            Value::Int(1) | Value::UInt(1) => Ok(true),
+            value => Err(conversion_error::<bool>(value)),
+        }
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        text(value)
+            .map(str::to_owned)
+            .ok_or_else(|| conversion_error::<String>(value))
+    }
+}
+
+impl FromValue for Vec<u8> {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::Bytes(bytes) => Ok(bytes.clone()),
+            Value::String(string) => Ok(string.clone().into_bytes()),
+            value => Err(conversion_error::<Vec<u8>>(value)),
+        }
+    }
+}
+
+fn text(value: &Value) -> Option<&str> {
+    match value {
+        Value::String(string) => Some(string),
+        Value::Bytes(bytes) => std::str::from_utf8(bytes).ok(),
+        _ => None,
+    }
+}
+
+/// The fields of a `Value::Date`, also parsed from `YYYY-MM-DD[ HH:MM:SS[.ffffff]]` text as
+/// dates are stored by databases without date types
+#[cfg(any(feature = "chrono", feature = "time"))]
+fn date_time(value: &Value) -> Option<(i32, u8, u8, u8, u8, u8, u32)> {
+    if let Value::Date(year, month, day, hour, minute, second, microsecond) = *value {
+        return Some((year.into(), month, day, hour, minute, second, microsecond));
+    }
+
+    let text = text(value)?;
+    let (date, time) = match text.split_once([' ', 'T']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (text, None),
+    };
+    let mut date = date.splitn(3, '-').map(str::parse::<u32>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    let (hour, minute, second, microsecond) = match time {
+        Some(time) => time_of_day(time)?,
+        None => (0, 0, 0, 0),
+    };
+    Some((
+        year.try_into().ok()?,
+        month.try_into().ok()?,
+        day.try_into().ok()?,
+        hour,
+        minute,
+        second,
+        microsecond,
+    ))
+}
+
+/// The fields of a non-negative `Value::Time` shorter than a day, also parsed from
+/// `HH:MM:SS[.ffffff]` text
+#[cfg(any(feature = "chrono", feature = "time"))]
+fn time_of_day_value(value: &Value) -> Option<(u8, u8, u8, u32)> {
+    match *value {
+        Value::Time(false, 0, hour, minute, second, microsecond) => {
+            Some((hour, minute, second, microsecond))
+        }
+        _ => time_of_day(text(value)?),
+    }
+}
+
+#[cfg(any(feature = "chrono", feature = "time"))]
+fn time_of_day(text: &str) -> Option<(u8, u8, u8, u32)> {
+    let (time, fraction) = match text.split_once('.') {
+        Some((time, fraction)) => (time, Some(fraction)),
+        None => (text, None),
+    };
+    let mut time = time.splitn(3, ':').map(str::parse::<u8>);
+    let (hour, minute, second) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+    let microsecond = match fraction {
+        Some(fraction) if (1..=6).contains(&fraction.len()) => {
+            fraction.parse::<u32>().ok()? * 10u32.pow(6 - fraction.len() as u32)
+        }
+        Some(_) => return None,
+        None => 0,
+    };
+    Some((hour, minute, second, microsecond))
+}
+
+/// The signed length of a `Value::Time` in microseconds
+#[cfg(any(feature = "chrono", feature = "time"))]
+fn duration_microseconds(value: &Value) -> Option<i64> {
+    let Value::Time(is_negative, days, hours, minutes, seconds, microseconds) = *value else {
+        return None;
+    };
+    let seconds = ((i64::from(days) * 24 + i64::from(hours)) * 60 + i64::from(minutes)) * 60
+        + i64::from(seconds);
+    let microseconds = seconds * 1_000_000 + i64::from(microseconds);
+    Some(if is_negative {
+        -microseconds
+    } else {
+        microseconds
+    })
+}
+
+#[cfg(feature = "chrono")]
+mod chrono_types {
+    use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+
+    use super::{conversion_error, date_time, duration_microseconds, time_of_day_value};
+    use crate::dbc::{Error, FromValue, Value};
+
+    /// Only dates without a time of day are converted, to not silently drop it
+    impl FromValue for NaiveDate {
+        fn from_value(value: &Value) -> Result<Self, Error> {
+            match date_time(value) {
+                Some((year, month, day, 0, 0, 0, 0)) => {
+                    NaiveDate::from_ymd_opt(year, month.into(), day.into())
+                }
+                _ => None,
+            }
+            .ok_or_else(|| conversion_error::<NaiveDate>(value))
+        }
+    }
+
+    impl FromValue for NaiveDateTime {
+        fn from_value(value: &Value) -> Result<Self, Error> {
+            date_time(value)
+                .and_then(|(year, month, day, hour, minute, second, microsecond)| {
+                    NaiveDate::from_ymd_opt(year, month.into(), day.into())?.and_hms_micro_opt(
+                        hour.into(),
+                        minute.into(),
+                        second.into(),
+                        microsecond,
+                    )
+                })
+                .ok_or_else(|| conversion_error::<NaiveDateTime>(value))
+        }
+    }
+
+    /// Timestamps are read as UTC
+    impl FromValue for DateTime<Utc> {
+        fn from_value(value: &Value) -> Result<Self, Error> {
+            NaiveDateTime::from_value(value)
+                .map(|date_time| Utc.from_utc_datetime(&date_time))
+                .map_err(|_| conversion_error::<DateTime<Utc>>(value))
+        }
+    }
+
+    impl FromValue for NaiveTime {
+        fn from_value(value: &Value) -> Result<Self, Error> {
+            time_of_day_value(value)
+                .and_then(|(hour, minute, second, microsecond)| {
+                    NaiveTime::from_hms_micro_opt(
+                        hour.into(),
+                        minute.into(),
+                        second.into(),
+                        microsecond,
+                    )
+                })
+                .ok_or_else(|| conversion_error::<NaiveTime>(value))
+        }
+    }
+
+    impl FromValue for Duration {
+        fn from_value(value: &Value) -> Result<Self, Error> {
+            duration_microseconds(value)
+                .map(Duration::microseconds)
+                .ok_or_else(|| conversion_error::<Duration>(value))
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+mod time_types {
+    use time::{Date, Duration, Month, OffsetDateTime, PrimitiveDateTime, Time};
+
+    use super::{conversion_error, date_time, duration_microseconds, time_of_day_value};
+    use crate::dbc::{Error, FromValue, Value};
+
+    /// Only dates without a time of day are converted, to not silently drop it
+    impl FromValue for Date {
+        fn from_value(value: &Value) -> Result<Self, Error> {
+            match date_time(value) {
+                Some((year, month, day, 0, 0, 0, 0)) => Month::try_from(month)
+                    .ok()
+                    .and_then(|month| Date::from_calendar_date(year, month, day).ok()),
+                _ => None,
+            }
+            .ok_or_else(|| conversion_error::<Date>(value))
+        }
+    }
+
+    impl FromValue for PrimitiveDateTime {
+        fn from_value(value: &Value) -> Result<Self, Error> {
+            date_time(value)
+                .and_then(|(year, month, day, hour, minute, second, microsecond)| {
+                    let date =
+                        Date::from_calendar_date(year, Month::try_from(month).ok()?, day).ok()?;
+                    let time = Time::from_hms_micro(hour, minute, second, microsecond).ok()?;
+                    Some(PrimitiveDateTime::new(date, time))
+                })
+                .ok_or_else(|| conversion_error::<PrimitiveDateTime>(value))
+        }
+    }
+
+    /// Timestamps are read as UTC
+    impl FromValue for OffsetDateTime {
+        fn from_value(value: &Value) -> Result<Self, Error> {
+            PrimitiveDateTime::from_value(value)
+                .map(PrimitiveDateTime::assume_utc)
+                .map_err(|_| conversion_error::<OffsetDateTime>(value))
+        }
+    }
+
+    impl FromValue for Time {
+        fn from_value(value: &Value) -> Result<Self, Error> {
+            time_of_day_value(value)
+                .and_then(|(hour, minute, second, microsecond)| {
+                    Time::from_hms_micro(hour, minute, second, microsecond).ok()
+                })
+                .ok_or_else(|| conversion_error::<Time>(value))
+        }
+    }
+
+    impl FromValue for Duration {
+        fn from_value(value: &Value) -> Result<Self, Error> {
+            duration_microseconds(value)
+                .map(Duration::microseconds)
+                .ok_or_else(|| conversion_error::<Duration>(value))
+        }
+    }
+}
+
+/// An error converting the value of a column, naming the column
+pub(crate) fn column_error(column: &Column, err: Error) -> Error {
+    Error::TypeConversion(
+        DatabaseError::new(format!("Invalid value in column {}: {}", column.name, err))
+            .with_source(err),
+    )
+}
+
+fn conversion_error<T>(value: &Value) -> Error {
+    let message = match value {
+        Value::NULL => format!(
+            "Cannot convert NULL to {}, read it as an Option instead",
+            std::any::type_name::<T>()
+        ),
+        // Blobs and texts can be large, only their length is relevant
+        Value::Bytes(bytes) => format!(
+            "Cannot convert {} bytes to {}",
+            bytes.len(),
+            std::any::type_name::<T>()
+        ),
+        Value::String(string) if string.len() > 32 => format!(
+            "Cannot convert a string of {} bytes to {}",
+            string.len(),
+            std::any::type_name::<T>()
+        ),
+        value => format!(
+            "Cannot convert {:?} to {}",
+            value,
+            std::any::type_name::<T>()
+        ),
+    };
+    Error::TypeConversion(DatabaseError::new(message))
+}
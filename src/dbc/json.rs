@@ -0,0 +1,106 @@
+use serde::ser::{SerializeMap, SerializeSeq, SerializeStruct};
+use serde::{Serialize, Serializer};
+
+use crate::dbc::{self, Column, DatabaseError, Error, QueryResult, Row, Value};
+
+/// The shapes `QueryResult::to_json` renders a result as
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum JsonFormat {
+    /// An array with an object per row, e.g. `[{"id":1,"name":"x"}]`
+    Objects,
+    /// The columns once and an array of values per row, e.g.
+    /// `{"columns":[{"name":"id","column_type":"INT"}],"rows":[[1]]}`
+    Columnar,
+    /// The derived `Serialize` form, with externally tagged values like `{"Int":1}`, which
+    /// deserializes back into a QueryResult without losing any type
+    #[default]
+    Tagged,
+}
+
+impl QueryResult {
+    /// Render the result as JSON. Except in the `Tagged` format, values are plain JSON values:
+    /// bytes are base64 encoded without padding and dates and times are ISO 8601 text.
+    pub fn to_json(&self, format: JsonFormat) -> Result<String, Error> {
+        let json = match format {
+            JsonFormat::Objects => serde_json::to_string(&ObjectRows(&self.rows)),
+            JsonFormat::Columnar => serde_json::to_string(&ColumnarRows(self)),
+            JsonFormat::Tagged => serde_json::to_string(self),
+        };
+        json.map_err(|err| Error::TypeConversion(DatabaseError::from_source(err)))
+    }
+}
+
+struct ObjectRows<'a>(&'a [Row]);
+
+impl Serialize for ObjectRows<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(ObjectRow))
+    }
+}
+
+struct ObjectRow<'a>(&'a Row);
+
+impl Serialize for ObjectRow<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.values.len()))?;
+        for (column, value) in self.0.columns.iter().zip(&self.0.values) {
+            map.serialize_entry(&column.name, &PlainValue(column, value))?;
+        }
+        map.end()
+    }
+}
+
+struct ColumnarRows<'a>(&'a QueryResult);
+
+impl Serialize for ColumnarRows<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut result = serializer.serialize_struct("QueryResult", 2)?;
+        result.serialize_field("columns", &self.0.columns)?;
+        result.serialize_field("rows", &ArrayRows(&self.0.rows))?;
+        result.end()
+    }
+}
+
+struct ArrayRows<'a>(&'a [Row]);
+
+impl Serialize for ArrayRows<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(ArrayRow))
+    }
+}
+
+struct ArrayRow<'a>(&'a Row);
+
+impl Serialize for ArrayRow<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.0.values.len()))?;
+        for (column, value) in self.0.columns.iter().zip(&self.0.values) {
+            seq.serialize_element(&PlainValue(column, value))?;
+        }
+        seq.end()
+    }
+}
+
+/// A value without its type tag, as a JSON null, boolean, number or string
+struct PlainValue<'a>(&'a Column, &'a Value);
+
+impl Serialize for PlainValue<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.1 {
+            Value::NULL => serializer.serialize_none(),
+            // Encoded as in the Tagged format
+            Value::Bytes(bytes) => dbc::base64::serialize(bytes, serializer),
+            Value::String(string) => serializer.serialize_str(string),
+            Value::Bool(bool) => serializer.serialize_bool(*bool),
+            Value::Int(int) => serializer.serialize_i64(*int),
+            Value::UInt(uint) => serializer.serialize_u64(*uint),
+            Value::Float(float) => serializer.serialize_f32(*float),
+            Value::Double(double) => serializer.serialize_f64(*double),
+            value => serializer.serialize_str(
+                &value
+                    .to_iso_text(&self.0.column_type)
+                    .expect("dates and times have an ISO 8601 text"),
+            ),
+        }
+    }
+}
@@ -1,8 +1,10 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use mysql;
-use mysql::prelude::Queryable;
+use mysql::prelude::{Protocol, Queryable};
 use mysql_common::constants::ColumnType;
+use sqlparser::dialect::{Dialect, MySqlDialect};
 
 use crate::dbc;
 
@@ -11,100 +13,461 @@ pub(crate) struct MySQLConnection {
 }
 
 impl MySQLConnection {
-    pub(crate) fn get_connection(url: &str) -> Result<Box<dyn dbc::Connection>, dbc::Error> {
+    pub(crate) fn get_connection(
+        options: &dbc::MySqlOptions,
+    ) -> Result<Box<dyn dbc::Connection + Send>, dbc::Error> {
+        let params = options
+            .params
+            .iter()
+            .cloned()
+            .collect::<HashMap<String, String>>();
+        let mut builder = mysql::OptsBuilder::new()
+            .from_hash_map(&params)
+            .map_err(|err| dbc::Error::Configuration(err.to_string()))?
+            .ip_or_hostname(Some(&options.host))
+            .tcp_port(options.port)
+            .user(options.user.as_ref())
+            .pass(options.password.as_ref())
+            .db_name(options.database.as_ref());
+        if options.connect_timeout.is_some() {
+            builder = builder.tcp_connect_timeout(options.connect_timeout);
+        }
+        if options.require_ssl {
+            builder = builder.ssl_opts(mysql::SslOpts::default());
+        }
         Ok(Box::new(MySQLConnection {
-            connection: mysql::Conn::new(url)?,
-        }) as Box<dyn dbc::Connection>)
+            connection: mysql::Conn::new(builder)?,
+        }) as Box<dyn dbc::Connection + Send>)
     }
 }
 
 impl dbc::Connection for MySQLConnection {
     /// Execute a query and return a dbc::QueryResult
     fn execute(&mut self, query: &str) -> Result<dbc::QueryResult, dbc::Error> {
-        // Automatically prepare the query to handle native MySQL types
-        return match self.connection.prep(query) {
-            Ok(stmt) => {
-                let result = self.connection.exec_iter(stmt, ())?;
-                let affected_rows = result.affected_rows() as usize;
-                let columns = result
-                    .columns()
-                    .as_ref()
-                    .iter()
-                    .map(|column| dbc::Column {
-                        name: column.name_str().to_string(),
-                        column_type: column.column_type().into(),
-                    })
-                    .collect::<Vec<dbc::Column>>();
-                let columns = Arc::from(columns);
-
-                let mut rows: Vec<dbc::Row> = Vec::new();
-                for row in result {
-                    let row = row.unwrap();
-                    let values: Vec<dbc::Value> = row
-                        .unwrap_raw()
-                        .iter()
-                        .map(|value| {
-                            if value.is_none() {
-                                dbc::Value::NULL
-                            } else {
-                                value.as_ref().unwrap().into()
-                            }
-                        })
-                        .collect();
-                    rows.push(dbc::Row {
-                        values,
-                        columns: Arc::clone(&columns),
-                    });
-                }
-                Ok(dbc::QueryResult {
-                    rows,
-                    affected_row_count: affected_rows,
-                })
+        execute(&mut self.connection, query)
+    }
+
+    fn execute_with_params(
+        &mut self,
+        query: &str,
+        params: &[dbc::Value],
+    ) -> Result<dbc::QueryResult, dbc::Error> {
+        if params.is_empty() {
+            return self.execute(query);
+        }
+        self.prepare(query)?.execute(params)
+    }
+
+    fn prepare(&mut self, query: &str) -> Result<Box<dyn dbc::Statement + '_>, dbc::Error> {
+        prepare(&mut self.connection, query)
+    }
+
+    fn query_iter(
+        &mut self,
+        query: &str,
+        params: &[dbc::Value],
+    ) -> Result<dbc::RowCursor<'_>, dbc::Error> {
+        query_iter(&mut self.connection, query, params)
+    }
+
+    fn transaction(&mut self) -> Result<Box<dyn dbc::TransactionConnection + '_>, dbc::Error> {
+        Ok(Box::new(MySQLTransaction {
+            transaction: self
+                .connection
+                .start_transaction(mysql::TxOpts::default())?,
+        }))
+    }
+
+    fn dialect(&self) -> &'static dyn Dialect {
+        &MySqlDialect {}
+    }
+
+    fn ping(&mut self) -> Result<(), dbc::Error> {
+        match self.connection.ping() {
+            true => Ok(()),
+            false => Err(dbc::Error::Connection(dbc::DatabaseError::new(
+                "The MySQL server did not respond to ping",
+            ))),
+        }
+    }
+}
+
+pub(crate) struct MySQLTransaction<'a> {
+    transaction: mysql::Transaction<'a>,
+}
+
+impl dbc::Connection for MySQLTransaction<'_> {
+    fn execute(&mut self, query: &str) -> Result<dbc::QueryResult, dbc::Error> {
+        execute(&mut self.transaction, query)
+    }
+
+    fn execute_with_params(
+        &mut self,
+        query: &str,
+        params: &[dbc::Value],
+    ) -> Result<dbc::QueryResult, dbc::Error> {
+        if params.is_empty() {
+            return self.execute(query);
+        }
+        self.prepare(query)?.execute(params)
+    }
+
+    fn prepare(&mut self, query: &str) -> Result<Box<dyn dbc::Statement + '_>, dbc::Error> {
+        prepare(&mut self.transaction, query)
+    }
+
+    fn query_iter(
+        &mut self,
+        query: &str,
+        params: &[dbc::Value],
+    ) -> Result<dbc::RowCursor<'_>, dbc::Error> {
+        query_iter(&mut self.transaction, query, params)
+    }
+
+    fn transaction(&mut self) -> Result<Box<dyn dbc::TransactionConnection + '_>, dbc::Error> {
+        Err(dbc::Error::Usage("Transactions cannot be nested".into()))
+    }
+
+    fn dialect(&self) -> &'static dyn Dialect {
+        &MySqlDialect {}
+    }
+}
+
+impl dbc::TransactionConnection for MySQLTransaction<'_> {
+    fn commit(self: Box<Self>) -> Result<(), dbc::Error> {
+        Ok(self.transaction.commit()?)
+    }
+
+    fn rollback(self: Box<Self>) -> Result<(), dbc::Error> {
+        Ok(self.transaction.rollback()?)
+    }
+
+    fn savepoint(
+        &mut self,
+        name: &str,
+    ) -> Result<Box<dyn dbc::TransactionConnection + '_>, dbc::Error> {
+        MySQLSavepoint::start(&mut self.transaction, name)
+    }
+}
+
+/// The mysql crate has no savepoint support, so savepoints are managed with SQL statements
+pub(crate) struct MySQLSavepoint<'a, 't> {
+    transaction: &'a mut mysql::Transaction<'t>,
+    name: String,
+    is_finished: bool,
+}
+
+impl<'a, 't> MySQLSavepoint<'a, 't> {
+    fn start(
+        transaction: &'a mut mysql::Transaction<'t>,
+        name: &str,
+    ) -> Result<Box<dyn dbc::TransactionConnection + 'a>, dbc::Error> {
+        let name = format!("`{}`", name.replace('`', "``"));
+        transaction.query_drop(format!("SAVEPOINT {}", name))?;
+        Ok(Box::new(MySQLSavepoint {
+            transaction,
+            name,
+            is_finished: false,
+        }))
+    }
+
+    fn finish(&mut self, rollback: bool) -> Result<(), dbc::Error> {
+        self.is_finished = true;
+        if rollback {
+            self.transaction
+                .query_drop(format!("ROLLBACK TO SAVEPOINT {}", self.name))?;
+        }
+        Ok(self
+            .transaction
+            .query_drop(format!("RELEASE SAVEPOINT {}", self.name))?)
+    }
+}
+
+impl Drop for MySQLSavepoint<'_, '_> {
+    fn drop(&mut self) {
+        if !self.is_finished {
+            let _ = self.finish(true);
+        }
+    }
+}
+
+impl dbc::Connection for MySQLSavepoint<'_, '_> {
+    fn execute(&mut self, query: &str) -> Result<dbc::QueryResult, dbc::Error> {
+        execute(self.transaction, query)
+    }
+
+    fn execute_with_params(
+        &mut self,
+        query: &str,
+        params: &[dbc::Value],
+    ) -> Result<dbc::QueryResult, dbc::Error> {
+        if params.is_empty() {
+            return self.execute(query);
+        }
+        self.prepare(query)?.execute(params)
+    }
+
+    fn prepare(&mut self, query: &str) -> Result<Box<dyn dbc::Statement + '_>, dbc::Error> {
+        prepare(self.transaction, query)
+    }
+
+    fn query_iter(
+        &mut self,
+        query: &str,
+        params: &[dbc::Value],
+    ) -> Result<dbc::RowCursor<'_>, dbc::Error> {
+        query_iter(self.transaction, query, params)
+    }
+
+    fn transaction(&mut self) -> Result<Box<dyn dbc::TransactionConnection + '_>, dbc::Error> {
+        Err(dbc::Error::Usage("Transactions cannot be nested".into()))
+    }
+
+    fn dialect(&self) -> &'static dyn Dialect {
+        &MySqlDialect {}
+    }
+}
+
+impl dbc::TransactionConnection for MySQLSavepoint<'_, '_> {
+    fn commit(mut self: Box<Self>) -> Result<(), dbc::Error> {
+        self.finish(false)
+    }
+
+    fn rollback(mut self: Box<Self>) -> Result<(), dbc::Error> {
+        self.finish(true)
+    }
+
+    fn savepoint(
+        &mut self,
+        name: &str,
+    ) -> Result<Box<dyn dbc::TransactionConnection + '_>, dbc::Error> {
+        MySQLSavepoint::start(self.transaction, name)
+    }
+}
+
+fn execute<Q: Queryable>(connection: &mut Q, query: &str) -> Result<dbc::QueryResult, dbc::Error> {
+    let kind = dbc::sql::statement_kind(&MySqlDialect {}, query);
+    // Automatically prepare the query to handle native MySQL types
+    match connection.prep(query) {
+        Ok(stmt) => {
+            let result = connection.exec_iter(stmt, ())?;
+            collect_result(result, kind)
+        }
+        Err(err) if is_unsupported_by_prepared_protocol(&err) => {
+            let result = connection.query_iter(query)?;
+            collect_result(result, kind)
+        }
+        Err(err) => Err(dbc::Error::from(err)),
+    }
+}
+
+fn query_iter<'a, Q: Queryable>(
+    connection: &'a mut Q,
+    query: &str,
+    params: &[dbc::Value],
+) -> Result<dbc::RowCursor<'a>, dbc::Error> {
+    if !params.is_empty() {
+        let result = connection.exec_iter(query, positional_params(params))?;
+        return Ok(row_cursor(result));
+    }
+
+    match connection.prep(query) {
+        Ok(stmt) => Ok(row_cursor(connection.exec_iter(stmt, ())?)),
+        Err(err) if is_unsupported_by_prepared_protocol(&err) => {
+            Ok(row_cursor(connection.query_iter(query)?))
+        }
+        Err(err) => Err(dbc::Error::from(err)),
+    }
+}
+
+fn is_unsupported_by_prepared_protocol(err: &mysql::Error) -> bool {
+    err.to_string()
+        .contains("This command is not supported in the prepared statement protocol yet")
+}
+
+fn positional_params(params: &[dbc::Value]) -> mysql::Params {
+    if params.is_empty() {
+        mysql::Params::Empty
+    } else {
+        mysql::Params::Positional(params.iter().map(mysql::Value::from).collect())
+    }
+}
+
+fn prepare<'a, Q: Queryable>(
+    connection: &'a mut Q,
+    query: &str,
+) -> Result<Box<dyn dbc::Statement + 'a>, dbc::Error> {
+    let statement = connection.prep(query)?;
+    let columns = statement
+        .columns()
+        .iter()
+        .map(|column| dbc::Column {
+            name: column.name_str().to_string(),
+            column_type: column.column_type().into(),
+        })
+        .collect::<Vec<dbc::Column>>();
+
+    Ok(Box::new(MySQLStatement {
+        connection,
+        statement,
+        columns: Arc::from(columns),
+        kind: dbc::sql::statement_kind(&MySqlDialect {}, query),
+    }))
+}
+
+pub(crate) struct MySQLStatement<'a, Q: Queryable> {
+    connection: &'a mut Q,
+    statement: mysql::Statement,
+    columns: Arc<[dbc::Column]>,
+    kind: dbc::StatementKind,
+}
+
+impl<Q: Queryable> dbc::Statement for MySQLStatement<'_, Q> {
+    fn execute(&mut self, params: &[dbc::Value]) -> Result<dbc::QueryResult, dbc::Error> {
+        let result = self
+            .connection
+            .exec_iter(&self.statement, positional_params(params))?;
+        collect_result(result, self.kind)
+    }
+
+    fn query(&mut self, params: &[dbc::Value]) -> Result<dbc::RowCursor<'_>, dbc::Error> {
+        let result = self
+            .connection
+            .exec_iter(&self.statement, positional_params(params))?;
+        Ok(row_cursor(result))
+    }
+
+    fn parameter_count(&self) -> usize {
+        self.statement.num_params() as usize
+    }
+
+    fn columns(&self) -> &[dbc::Column] {
+        &self.columns
+    }
+}
+
+fn collect_result<P: Protocol>(
+    result: mysql::QueryResult<'_, '_, '_, P>,
+    kind: dbc::StatementKind,
+) -> Result<dbc::QueryResult, dbc::Error> {
+    let affected_rows = result.affected_rows() as usize;
+    let mut result = dbc::QueryResult::try_from(row_cursor(result))?;
+    result.affected_row_count = affected_rows;
+    Ok(result.with_kind(kind))
+}
+
+fn row_cursor<'a, 't: 'a, 'tc: 'a, P: Protocol + 'a>(
+    result: mysql::QueryResult<'a, 't, 'tc, P>,
+) -> dbc::RowCursor<'a> {
+    let columns = result
+        .columns()
+        .as_ref()
+        .iter()
+        .map(|column| dbc::Column {
+            name: column.name_str().to_string(),
+            column_type: column.column_type().into(),
+        })
+        .collect::<Arc<[dbc::Column]>>();
+
+    let is_textual = result
+        .columns()
+        .as_ref()
+        .iter()
+        .map(is_textual)
+        .collect::<Vec<bool>>();
+
+    let row_columns = Arc::clone(&columns);
+    let rows = result.map(move |row| {
+        // Values are only missing from a raw row once they have been taken out of it
+        let values = row?
+            .unwrap_raw()
+            .into_iter()
+            .zip(&is_textual)
+            .map(|(value, is_textual)| match value {
+                Some(mysql::Value::Bytes(bytes)) if *is_textual => Ok(dbc::Value::from_text(bytes)),
+                Some(value) => Ok(dbc::Value::from(&value)),
+                None => Err(dbc::Error::Database(dbc::DatabaseError::new(
+                    "Missing value in MySQL row",
+                ))),
+            })
+            .collect::<Result<Vec<dbc::Value>, dbc::Error>>()?;
+        Ok(dbc::Row {
+            values,
+            columns: Arc::clone(&row_columns),
+        })
+    });
+    dbc::RowCursor::new(columns, rows)
+}
+
+/// The character set MySQL reports for binary strings and blobs
+const BINARY_CHARACTER_SET: u16 = 63;
+
+/// Whether the values of a column are text, as opposed to binary strings or numbers
+fn is_textual(column: &mysql::Column) -> bool {
+    match column.column_type() {
+        ColumnType::MYSQL_TYPE_VARCHAR
+        | ColumnType::MYSQL_TYPE_VAR_STRING
+        | ColumnType::MYSQL_TYPE_STRING
+        | ColumnType::MYSQL_TYPE_TINY_BLOB
+        | ColumnType::MYSQL_TYPE_MEDIUM_BLOB
+        | ColumnType::MYSQL_TYPE_LONG_BLOB
+        | ColumnType::MYSQL_TYPE_BLOB
+        | ColumnType::MYSQL_TYPE_ENUM
+        | ColumnType::MYSQL_TYPE_SET => column.character_set() != BINARY_CHARACTER_SET,
+        // Decimals are sent as their text
+        ColumnType::MYSQL_TYPE_JSON
+        | ColumnType::MYSQL_TYPE_DECIMAL
+        | ColumnType::MYSQL_TYPE_NEWDECIMAL => true,
+        _ => false,
+    }
+}
+
+impl From<mysql::Error> for dbc::Error {
+    fn from(err: mysql::Error) -> Self {
+        use mysql::DriverError;
+
+        match &err {
+            mysql::Error::MySqlError(server_error) => {
+                let code = server_error.code;
+                let sql_state = server_error.state.clone();
+                let error = dbc::DatabaseError::from_source(err)
+                    .with_sql_state(sql_state)
+                    .with_code(code.into());
+                // MySQL reports all constraint violations with the same SQLSTATE, the error code
+                // tells them apart
+                let kind = match code {
+                    1062 | 1586 => dbc::ConstraintKind::Unique,
+                    1216 | 1217 | 1451 | 1452 => dbc::ConstraintKind::ForeignKey,
+                    1048 | 1364 => dbc::ConstraintKind::NotNull,
+                    3819 => dbc::ConstraintKind::Check,
+                    1064 | 1149 => return dbc::Error::Syntax(error),
+                    1205 => return dbc::Error::Timeout(error),
+                    1213 => return dbc::Error::Deadlock(error),
+                    _ => return dbc::Error::from_sql_state(error),
+                };
+                dbc::Error::ConstraintViolation { kind, error }
+            }
+            mysql::Error::DriverError(DriverError::Timeout) => {
+                dbc::Error::Timeout(dbc::DatabaseError::from_source(err))
+            }
+            mysql::Error::DriverError(
+                DriverError::MismatchedStmtParams(..)
+                | DriverError::MissingNamedParameter(_)
+                | DriverError::NamedParamsForPositionalQuery
+                | DriverError::MixedParams,
+            ) => dbc::Error::Usage(err.to_string()),
+            mysql::Error::IoError(_)
+            | mysql::Error::CodecError(_)
+            | mysql::Error::DriverError(_) => {
+                dbc::Error::Connection(dbc::DatabaseError::from_source(err))
             }
-            Err(err) => {
-                if err.to_string().contains(
-                    "This command is not supported in the prepared statement protocol yet",
-                ) {
-                    let result = self.connection.query_iter(query)?;
-                    let affected_rows = result.affected_rows() as usize;
-                    let columns = result
-                        .columns()
-                        .as_ref()
-                        .iter()
-                        .map(|column| dbc::Column {
-                            name: column.name_str().to_string(),
-                            column_type: column.column_type().into(),
-                        })
-                        .collect::<Vec<dbc::Column>>();
-                    let columns = Arc::from(columns);
-
-                    let mut rows: Vec<dbc::Row> = Vec::new();
-                    for row in result {
-                        let row = row.unwrap();
-                        let values: Vec<dbc::Value> = row
-                            .unwrap_raw()
-                            .iter()
-                            .map(|value| {
-                                if value.is_none() {
-                                    dbc::Value::NULL
-                                } else {
-                                    value.as_ref().unwrap().into()
-                                }
-                            })
-                            .collect();
-                        rows.push(dbc::Row {
-                            values,
-                            columns: Arc::clone(&columns),
-                        });
-                    }
-                    return Ok(dbc::QueryResult {
-                        rows,
-                        affected_row_count: affected_rows,
-                    });
-                }
-                Err(dbc::Error::from(err))
+            mysql::Error::UrlError(_) => dbc::Error::Configuration(err.to_string()),
+            mysql::Error::FromValueError(_) | mysql::Error::FromRowError(_) => {
+                dbc::Error::TypeConversion(dbc::DatabaseError::from_source(err))
             }
-        };
+            #[allow(unreachable_patterns)]
+            _ => dbc::Error::Database(dbc::DatabaseError::from_source(err)),
+        }
     }
 }
 
@@ -127,6 +490,27 @@ impl From<&mysql::Value> for dbc::Value {
     }
 }
 
+impl From<&dbc::Value> for mysql::Value {
+    fn from(value: &dbc::Value) -> Self {
+        match value {
+            dbc::Value::NULL => mysql::Value::NULL,
+            dbc::Value::Bytes(bytes) => mysql::Value::Bytes(bytes.clone()),
+            dbc::Value::String(string) => mysql::Value::Bytes(string.clone().into_bytes()),
+            dbc::Value::Bool(bool) => mysql::Value::Int(*bool as i64),
+            dbc::Value::Int(int) => mysql::Value::Int(*int),
+            dbc::Value::UInt(uint) => mysql::Value::UInt(*uint),
+            dbc::Value::Float(float) => mysql::Value::Float(*float),
+            dbc::Value::Double(double) => mysql::Value::Double(*double),
+            dbc::Value::Date(year, month, day, hour, minute, second, microsecond) => {
+                mysql::Value::Date(*year, *month, *day, *hour, *minute, *second, *microsecond)
+            }
+            dbc::Value::Time(negative, days, hours, minutes, seconds, microseconds) => {
+                mysql::Value::Time(*negative, *days, *hours, *minutes, *seconds, *microseconds)
+            }
+        }
+    }
+}
+
 impl From<ColumnType> for dbc::ColumnType {
     fn from(column_type: ColumnType) -> Self {
         match column_type {
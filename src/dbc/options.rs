@@ -0,0 +1,729 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::dbc::Error;
+
+/// Options for connecting to a database, which can be parsed from and rendered back to a URL
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConnectOptions {
+    Sqlite(SqliteOptions),
+    MySql(MySqlOptions),
+    Postgres(PostgresOptions),
+}
+
+impl ConnectOptions {
+    pub fn from_url(url: &str) -> Result<Self, Error> {
+        match url {
+            url if url.starts_with("mysql://") => Ok(MySqlOptions::from_url(url)?.into()),
+            url if url.starts_with("postgres://") || url.starts_with("postgresql://") => {
+                Ok(PostgresOptions::from_url(url)?.into())
+            }
+            url if url.starts_with("sqlite://") => Ok(SqliteOptions::from_url(url)?.into()),
+            _ => Err(Error::UnsupportedScheme(
+                url.split_once("://")
+                    .map_or(url, |(scheme, _)| scheme)
+                    .to_owned(),
+            )),
+        }
+    }
+
+    /// Check the options before connecting, so that obviously invalid ones are reported without
+    /// reaching out to the database
+    pub fn validate(&self) -> Result<(), Error> {
+        let (host, port) = match self {
+            ConnectOptions::Sqlite(options) if options.path.is_empty() => {
+                return Err(Error::Configuration("Missing SQLite database path".into()))
+            }
+            ConnectOptions::Sqlite(_) => return Ok(()),
+            ConnectOptions::MySql(options) => (&options.host, options.port),
+            ConnectOptions::Postgres(options) => (&options.host, options.port),
+        };
+        if host.is_empty() {
+            return Err(Error::Configuration("Missing database host".into()));
+        }
+        if port == 0 {
+            return Err(Error::Configuration("Invalid database port 0".into()));
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ConnectOptions {
+    type Err = Error;
+
+    fn from_str(url: &str) -> Result<Self, Error> {
+        ConnectOptions::from_url(url)
+    }
+}
+
+/// Render the options as a URL accepted by `ConnectOptions::from_url`
+impl fmt::Display for ConnectOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectOptions::Sqlite(options) => options.fmt(f),
+            ConnectOptions::MySql(options) => options.fmt(f),
+            ConnectOptions::Postgres(options) => options.fmt(f),
+        }
+    }
+}
+
+impl From<SqliteOptions> for ConnectOptions {
+    fn from(options: SqliteOptions) -> Self {
+        ConnectOptions::Sqlite(options)
+    }
+}
+
+impl From<MySqlOptions> for ConnectOptions {
+    fn from(options: MySqlOptions) -> Self {
+        ConnectOptions::MySql(options)
+    }
+}
+
+impl From<PostgresOptions> for ConnectOptions {
+    fn from(options: PostgresOptions) -> Self {
+        ConnectOptions::Postgres(options)
+    }
+}
+
+macro_rules! url_option_enum {
+    ($(#[$meta:meta])* $name:ident { $($variant:ident => $value:literal),* $(,)? }) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub enum $name {
+            $($variant),*
+        }
+
+        impl $name {
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $($name::$variant => $value),*
+                }
+            }
+
+            fn parse(name: &str, value: &str) -> Result<Self, Error> {
+                match value.to_ascii_lowercase().as_str() {
+                    $($value => Ok($name::$variant),)*
+                    _ => Err(invalid_option(name, value)),
+                }
+            }
+        }
+    };
+}
+
+url_option_enum!(
+    /// How the database file is opened, the `mode` URL option
+    SqliteMode {
+        ReadOnly => "ro",
+        ReadWrite => "rw",
+        ReadWriteCreate => "rwc",
+        Memory => "memory",
+    }
+);
+
+url_option_enum!(
+    /// The `cache` URL option
+    SqliteCache {
+        Shared => "shared",
+        Private => "private",
+    }
+);
+
+url_option_enum!(
+    /// The `journal_mode` URL option
+    SqliteJournalMode {
+        Delete => "delete",
+        Truncate => "truncate",
+        Persist => "persist",
+        Memory => "memory",
+        Wal => "wal",
+        Off => "off",
+    }
+);
+
+url_option_enum!(
+    /// The `sslmode` URL option. Connections are made without TLS, so `Require` fails to connect.
+    PostgresSslMode {
+        Disable => "disable",
+        Prefer => "prefer",
+        Require => "require",
+    }
+);
+
+/// Options of a `sqlite://<path>[?<option>=<value>&...]` URL. The path is relative to the
+/// working directory unless it starts with `/`, and can also be `:memory:` or a `file:` URI,
+/// which keeps the options not handled here for SQLite to interpret, e.g. `vfs` or `immutable`.
+/// The path is percent-decoded, except for `file:` URIs which SQLite decodes itself.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SqliteOptions {
+    pub(crate) path: String,
+    pub(crate) mode: Option<SqliteMode>,
+    pub(crate) cache: Option<SqliteCache>,
+    pub(crate) busy_timeout: Option<Duration>,
+    pub(crate) journal_mode: Option<SqliteJournalMode>,
+    pub(crate) foreign_keys: Option<bool>,
+    pub(crate) uri_params: Vec<(String, String)>,
+}
+
+impl SqliteOptions {
+    pub fn new(path: &str) -> Self {
+        SqliteOptions {
+            path: path.to_owned(),
+            mode: None,
+            cache: None,
+            busy_timeout: None,
+            journal_mode: None,
+            foreign_keys: None,
+            uri_params: Vec::new(),
+        }
+    }
+
+    /// A private in-memory database
+    pub fn memory() -> Self {
+        SqliteOptions::new(":memory:")
+    }
+
+    pub fn from_url(url: &str) -> Result<Self, Error> {
+        let url = url
+            .strip_prefix("sqlite://")
+            .ok_or_else(|| Error::Configuration("SQLite URLs must start with sqlite://".into()))?;
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        if path.is_empty() {
+            return Err(Error::Configuration("Missing SQLite database path".into()));
+        }
+
+        let mut options = if path.starts_with("file:") {
+            SqliteOptions::new(path)
+        } else {
+            SqliteOptions::new(&percent_decode(path)?)
+        };
+        for (name, value) in parse_query(query)? {
+            match name.as_str() {
+                "mode" => options.mode = Some(SqliteMode::parse(&name, &value)?),
+                "cache" => options.cache = Some(SqliteCache::parse(&name, &value)?),
+                "busy_timeout" => {
+                    let milliseconds = value
+                        .parse::<u64>()
+                        .map_err(|_| invalid_option(&name, &value))?;
+                    options.busy_timeout = Some(Duration::from_millis(milliseconds));
+                }
+                "journal_mode" => {
+                    options.journal_mode = Some(SqliteJournalMode::parse(&name, &value)?)
+                }
+                "foreign_keys" => options.foreign_keys = Some(parse_bool(&name, &value)?),
+                _ if path.starts_with("file:") => options.uri_params.push((name, value)),
+                _ => {
+                    return Err(Error::Configuration(format!(
+                        "Unsupported SQLite option {}",
+                        name
+                    )))
+                }
+            }
+        }
+        Ok(options)
+    }
+
+    pub fn mode(mut self, mode: SqliteMode) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    pub fn cache(mut self, cache: SqliteCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// How long to wait for a locked database before failing
+    pub fn busy_timeout(mut self, busy_timeout: Duration) -> Self {
+        self.busy_timeout = Some(busy_timeout);
+        self
+    }
+
+    pub fn journal_mode(mut self, journal_mode: SqliteJournalMode) -> Self {
+        self.journal_mode = Some(journal_mode);
+        self
+    }
+
+    pub fn foreign_keys(mut self, foreign_keys: bool) -> Self {
+        self.foreign_keys = Some(foreign_keys);
+        self
+    }
+}
+
+impl fmt::Display for SqliteOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut params = Vec::new();
+        if let Some(mode) = self.mode {
+            params.push(("mode", mode.as_str().to_owned()));
+        }
+        if let Some(cache) = self.cache {
+            params.push(("cache", cache.as_str().to_owned()));
+        }
+        if let Some(busy_timeout) = self.busy_timeout {
+            params.push(("busy_timeout", busy_timeout.as_millis().to_string()));
+        }
+        if let Some(journal_mode) = self.journal_mode {
+            params.push(("journal_mode", journal_mode.as_str().to_owned()));
+        }
+        if let Some(foreign_keys) = self.foreign_keys {
+            params.push((
+                "foreign_keys",
+                if foreign_keys { "on" } else { "off" }.to_owned(),
+            ));
+        }
+        params.extend(
+            self.uri_params
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.clone())),
+        );
+        if self.path.starts_with("file:") {
+            write!(f, "sqlite://{}", self.path)?;
+        } else {
+            write!(f, "sqlite://{}", percent_encode_path(&self.path))?;
+        }
+        write_query(f, &params)
+    }
+}
+
+/// Options of a `mysql://[<user>[:<password>]@]<host>[:<port>][/<database>][?<params>]` URL.
+/// URL parameters other than `require_ssl` and `tcp_connect_timeout_ms` are passed on to the
+/// mysql crate, see `mysql::Opts` for the supported ones.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MySqlOptions {
+    pub(crate) host: String,
+    pub(crate) port: u16,
+    pub(crate) user: Option<String>,
+    pub(crate) password: Option<String>,
+    pub(crate) database: Option<String>,
+    pub(crate) require_ssl: bool,
+    pub(crate) connect_timeout: Option<Duration>,
+    pub(crate) params: Vec<(String, String)>,
+}
+
+impl Default for MySqlOptions {
+    fn default() -> Self {
+        MySqlOptions {
+            host: "localhost".to_owned(),
+            port: 3306,
+            user: None,
+            password: None,
+            database: None,
+            require_ssl: false,
+            connect_timeout: None,
+            params: Vec::new(),
+        }
+    }
+}
+
+impl MySqlOptions {
+    pub fn new() -> Self {
+        MySqlOptions::default()
+    }
+
+    pub fn from_url(url: &str) -> Result<Self, Error> {
+        let url = ServerUrl::parse(url, &["mysql://"])?;
+        let mut options = MySqlOptions {
+            user: url.user,
+            password: url.password,
+            database: url.database,
+            ..MySqlOptions::default()
+        };
+        if let Some(host) = url.host {
+            options.host = host;
+        }
+        if let Some(port) = url.port {
+            options.port = port;
+        }
+        for (name, value) in url.params {
+            match name.as_str() {
+                "require_ssl" => options.require_ssl = parse_bool(&name, &value)?,
+                "tcp_connect_timeout_ms" => {
+                    let milliseconds = value
+                        .parse::<u64>()
+                        .map_err(|_| invalid_option(&name, &value))?;
+                    options.connect_timeout = Some(Duration::from_millis(milliseconds));
+                }
+                _ => options.params.push((name, value)),
+            }
+        }
+        Ok(options)
+    }
+
+    pub fn host(mut self, host: &str) -> Self {
+        self.host = host.to_owned();
+        self
+    }
+
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    pub fn user(mut self, user: &str) -> Self {
+        self.user = Some(user.to_owned());
+        self
+    }
+
+    pub fn password(mut self, password: &str) -> Self {
+        self.password = Some(password.to_owned());
+        self
+    }
+
+    pub fn database(mut self, database: &str) -> Self {
+        self.database = Some(database.to_owned());
+        self
+    }
+
+    /// Whether the connection must use TLS
+    pub fn require_ssl(mut self, require_ssl: bool) -> Self {
+        self.require_ssl = require_ssl;
+        self
+    }
+
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    /// Set a URL parameter understood by the mysql crate
+    pub fn param(mut self, name: &str, value: &str) -> Self {
+        self.params.push((name.to_owned(), value.to_owned()));
+        self
+    }
+}
+
+impl fmt::Display for MySqlOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut params = Vec::new();
+        if self.require_ssl {
+            params.push(("require_ssl", "true".to_owned()));
+        }
+        if let Some(connect_timeout) = self.connect_timeout {
+            params.push((
+                "tcp_connect_timeout_ms",
+                connect_timeout.as_millis().to_string(),
+            ));
+        }
+        params.extend(
+            self.params
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.clone())),
+        );
+        write_server_url(
+            f,
+            "mysql",
+            self.user.as_deref(),
+            self.password.as_deref(),
+            &self.host,
+            self.port,
+            self.database.as_deref(),
+        )?;
+        write_query(f, &params)
+    }
+}
+
+/// Options of a `postgres://[<user>[:<password>]@]<host>[:<port>][/<database>][?<params>]` URL.
+/// URL parameters other than `sslmode` and `connect_timeout` are passed on to the postgres
+/// crate, see `postgres::Config` for the supported ones.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PostgresOptions {
+    pub(crate) host: String,
+    pub(crate) port: u16,
+    pub(crate) user: Option<String>,
+    pub(crate) password: Option<String>,
+    pub(crate) database: Option<String>,
+    pub(crate) ssl_mode: Option<PostgresSslMode>,
+    pub(crate) connect_timeout: Option<Duration>,
+    pub(crate) params: Vec<(String, String)>,
+}
+
+impl Default for PostgresOptions {
+    fn default() -> Self {
+        PostgresOptions {
+            host: "localhost".to_owned(),
+            port: 5432,
+            user: None,
+            password: None,
+            database: None,
+            ssl_mode: None,
+            connect_timeout: None,
+            params: Vec::new(),
+        }
+    }
+}
+
+impl PostgresOptions {
+    pub fn new() -> Self {
+        PostgresOptions::default()
+    }
+
+    pub fn from_url(url: &str) -> Result<Self, Error> {
+        let url = ServerUrl::parse(url, &["postgres://", "postgresql://"])?;
+        let mut options = PostgresOptions {
+            user: url.user,
+            password: url.password,
+            database: url.database,
+            ..PostgresOptions::default()
+        };
+        if let Some(host) = url.host {
+            options.host = host;
+        }
+        if let Some(port) = url.port {
+            options.port = port;
+        }
+        for (name, value) in url.params {
+            match name.as_str() {
+                "sslmode" => options.ssl_mode = Some(PostgresSslMode::parse(&name, &value)?),
+                "connect_timeout" => {
+                    let seconds = value
+                        .parse::<u64>()
+                        .map_err(|_| invalid_option(&name, &value))?;
+                    options.connect_timeout = Some(Duration::from_secs(seconds));
+                }
+                _ => options.params.push((name, value)),
+            }
+        }
+        Ok(options)
+    }
+
+    pub fn host(mut self, host: &str) -> Self {
+        self.host = host.to_owned();
+        self
+    }
+
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    pub fn user(mut self, user: &str) -> Self {
+        self.user = Some(user.to_owned());
+        self
+    }
+
+    pub fn password(mut self, password: &str) -> Self {
+        self.password = Some(password.to_owned());
+        self
+    }
+
+    pub fn database(mut self, database: &str) -> Self {
+        self.database = Some(database.to_owned());
+        self
+    }
+
+    pub fn ssl_mode(mut self, ssl_mode: PostgresSslMode) -> Self {
+        self.ssl_mode = Some(ssl_mode);
+        self
+    }
+
+    /// Rendered in whole seconds in URLs
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    /// Set a URL parameter understood by the postgres crate
+    pub fn param(mut self, name: &str, value: &str) -> Self {
+        self.params.push((name.to_owned(), value.to_owned()));
+        self
+    }
+}
+
+impl fmt::Display for PostgresOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut params = Vec::new();
+        if let Some(ssl_mode) = self.ssl_mode {
+            params.push(("sslmode", ssl_mode.as_str().to_owned()));
+        }
+        if let Some(connect_timeout) = self.connect_timeout {
+            params.push(("connect_timeout", connect_timeout.as_secs().to_string()));
+        }
+        params.extend(
+            self.params
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.clone())),
+        );
+        write_server_url(
+            f,
+            "postgres",
+            self.user.as_deref(),
+            self.password.as_deref(),
+            &self.host,
+            self.port,
+            self.database.as_deref(),
+        )?;
+        write_query(f, &params)
+    }
+}
+
+/// The percent-decoded parts of a database server URL
+struct ServerUrl {
+    user: Option<String>,
+    password: Option<String>,
+    host: Option<String>,
+    port: Option<u16>,
+    database: Option<String>,
+    params: Vec<(String, String)>,
+}
+
+impl ServerUrl {
+    fn parse(url: &str, schemes: &[&str]) -> Result<Self, Error> {
+        let rest = schemes
+            .iter()
+            .find_map(|scheme| url.strip_prefix(scheme))
+            .ok_or_else(|| {
+                Error::Configuration(format!("URL must start with {}", schemes.join(" or ")))
+            })?;
+        let (rest, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let (authority, database) = rest.split_once('/').unwrap_or((rest, ""));
+        let (credentials, host) = match authority.rsplit_once('@') {
+            Some((credentials, host)) => (Some(credentials), host),
+            None => (None, authority),
+        };
+        let (user, password) = match credentials.map(|credentials| credentials.split_once(':')) {
+            Some(Some((user, password))) => (Some(user), Some(password)),
+            Some(None) => (credentials, None),
+            None => (None, None),
+        };
+
+        // IPv6 addresses are enclosed in brackets to separate them from the port
+        let (host, port) = match host.strip_prefix('[') {
+            Some(host) => {
+                let (host, port) = host
+                    .split_once(']')
+                    .ok_or_else(|| Error::Configuration("Unclosed [ in URL host".into()))?;
+                (host, port.strip_prefix(':'))
+            }
+            None => match host.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (host, None),
+            },
+        };
+        let port = port
+            .map(|port| {
+                port.parse::<u16>()
+                    .map_err(|_| Error::Configuration(format!("Invalid URL port {}", port)))
+            })
+            .transpose()?;
+
+        let decode_non_empty = |part: Option<&str>| {
+            part.filter(|part| !part.is_empty())
+                .map(percent_decode)
+                .transpose()
+        };
+        Ok(ServerUrl {
+            user: decode_non_empty(user)?,
+            password: password.map(percent_decode).transpose()?,
+            host: decode_non_empty(Some(host))?,
+            port,
+            database: decode_non_empty(Some(database))?,
+            params: parse_query(query)?,
+        })
+    }
+}
+
+fn write_server_url(
+    f: &mut fmt::Formatter<'_>,
+    scheme: &str,
+    user: Option<&str>,
+    password: Option<&str>,
+    host: &str,
+    port: u16,
+    database: Option<&str>,
+) -> fmt::Result {
+    write!(f, "{}://", scheme)?;
+    if let Some(user) = user {
+        write!(f, "{}", percent_encode(user))?;
+    }
+    if let Some(password) = password {
+        write!(f, ":{}", percent_encode(password))?;
+    }
+    if user.is_some() || password.is_some() {
+        write!(f, "@")?;
+    }
+    if host.contains(':') {
+        write!(f, "[{}]:{}", host, port)?;
+    } else {
+        write!(f, "{}:{}", percent_encode(host), port)?;
+    }
+    if let Some(database) = database {
+        write!(f, "/{}", percent_encode(database))?;
+    }
+    Ok(())
+}
+
+fn parse_query(query: &str) -> Result<Vec<(String, String)>, Error> {
+    query
+        .split('&')
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let (name, value) = param.split_once('=').unwrap_or((param, ""));
+            Ok((percent_decode(name)?, percent_decode(value)?))
+        })
+        .collect()
+}
+
+fn write_query(f: &mut fmt::Formatter<'_>, params: &[(&str, String)]) -> fmt::Result {
+    for (i, (name, value)) in params.iter().enumerate() {
+        let separator = if i == 0 { '?' } else { '&' };
+        write!(
+            f,
+            "{}{}={}",
+            separator,
+            percent_encode(name),
+            percent_encode(value)
+        )?;
+    }
+    Ok(())
+}
+
+fn percent_decode(text: &str) -> Result<String, Error> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte != b'%' {
+            bytes.push(byte);
+            rest = tail;
+            continue;
+        }
+        let hex = tail
+            .get(..2)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            .ok_or_else(|| Error::Configuration(format!("Invalid percent-encoding in {}", text)))?;
+        bytes.push(hex);
+        rest = &tail[2..];
+    }
+    String::from_utf8(bytes).map_err(|_| Error::Configuration(format!("Invalid UTF-8 in {}", text)))
+}
+
+pub(crate) fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Percent-encode a path, keeping its `/` separators and the colons of `:memory:`
+fn percent_encode_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| percent_encode(segment).replace("%3A", ":"))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn parse_bool(name: &str, value: &str) -> Result<bool, Error> {
+    match value.to_ascii_lowercase().as_str() {
+        "on" | "true" | "1" => Ok(true),
+        "off" | "false" | "0" => Ok(false),
+        _ => Err(invalid_option(name, value)),
+    }
+}
+
+fn invalid_option(name: &str, value: &str) -> Error {
+    Error::Configuration(format!("Invalid value {} for option {}", value, name))
+}
@@ -0,0 +1,297 @@
+use std::collections::VecDeque;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, Weak};
+use std::time::{Duration, Instant};
+
+use crate::dbc::{Database, DatabaseError, Error};
+
+/// A pool of connections to the same database which can be shared between threads.
+/// Cloning a pool is cheap, the clones share the same connections.
+#[derive(Clone)]
+pub struct Pool {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    url: String,
+    options: PoolBuilder,
+    state: Mutex<State>,
+    // Signalled whenever a connection is returned or a slot for a new one frees up
+    available: Condvar,
+}
+
+struct State {
+    // Least recently used first
+    idle: VecDeque<IdleConnection>,
+    // The number of open connections, both idle and checked out
+    size: usize,
+}
+
+struct IdleConnection {
+    database: Database,
+    idle_since: Instant,
+}
+
+#[derive(Clone, Debug)]
+pub struct PoolBuilder {
+    min_size: usize,
+    max_size: usize,
+    checkout_timeout: Duration,
+    idle_timeout: Option<Duration>,
+    test_on_checkout: bool,
+}
+
+impl Default for PoolBuilder {
+    fn default() -> Self {
+        PoolBuilder {
+            min_size: 0,
+            max_size: 10,
+            checkout_timeout: Duration::from_secs(30),
+            idle_timeout: Some(Duration::from_secs(600)),
+            test_on_checkout: true,
+        }
+    }
+}
+
+impl PoolBuilder {
+    /// The number of connections opened when the pool is built and kept open while idle
+    pub fn min_size(mut self, min_size: usize) -> Self {
+        self.min_size = min_size;
+        self
+    }
+
+    /// The maximum number of open connections, checkouts wait when all of them are in use
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// How long a checkout waits for a connection before giving up
+    pub fn checkout_timeout(mut self, checkout_timeout: Duration) -> Self {
+        self.checkout_timeout = checkout_timeout;
+        self
+    }
+
+    /// How long a connection can stay idle before it is closed, `None` to keep them open.
+    /// Idle connections are only closed while there are more than `min_size` of them open,
+    /// by a background thread which stops when the pool is dropped.
+    pub fn idle_timeout(mut self, idle_timeout: Option<Duration>) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Whether idle connections are pinged before being checked out, replacing broken ones
+    pub fn test_on_checkout(mut self, test_on_checkout: bool) -> Self {
+        self.test_on_checkout = test_on_checkout;
+        self
+    }
+
+    pub fn build(self, url: &str) -> Result<Pool, Error> {
+        if self.max_size == 0 {
+            return Err(Error::Configuration(
+                "The maximum pool size must be at least 1".into(),
+            ));
+        }
+        if self.min_size > self.max_size {
+            return Err(Error::Configuration(
+                "The minimum pool size cannot exceed the maximum pool size".into(),
+            ));
+        }
+
+        let idle = (0..self.min_size)
+            .map(|_| {
+                Ok(IdleConnection {
+                    database: Database::new(url)?,
+                    idle_since: Instant::now(),
+                })
+            })
+            .collect::<Result<VecDeque<IdleConnection>, Error>>()?;
+        let idle_timeout = self.idle_timeout;
+        let shared = Arc::new(Shared {
+            url: url.to_owned(),
+            options: self,
+            state: Mutex::new(State {
+                size: idle.len(),
+                idle,
+            }),
+            available: Condvar::new(),
+        });
+        if let Some(idle_timeout) = idle_timeout {
+            spawn_reaper(Arc::downgrade(&shared), idle_timeout)?;
+        }
+        Ok(Pool { shared })
+    }
+}
+
+/// Close the connections which have been idle for too long, also while the pool is not used.
+/// The thread only holds on to the pool while checking it, and stops once the pool is dropped.
+fn spawn_reaper(shared: Weak<Shared>, idle_timeout: Duration) -> Result<(), Error> {
+    let reaper = move || {
+        while let Some(shared) = shared.upgrade() {
+            let mut state = shared.lock();
+            let expired = shared.evict_expired(&mut state);
+            // Sleep until the least recently used connection expires
+            let next_check = state
+                .idle
+                .front()
+                .map(|idle| idle_timeout.saturating_sub(idle.idle_since.elapsed()))
+                .filter(|remaining| !remaining.is_zero())
+                .unwrap_or(idle_timeout);
+            drop(state);
+            if !expired.is_empty() {
+                drop(expired);
+                shared.available.notify_all();
+            }
+            drop(shared);
+            std::thread::sleep(next_check);
+        }
+    };
+    std::thread::Builder::new()
+        .name("rdbc2-pool-reaper".to_owned())
+        .spawn(reaper)
+        .map(drop)
+        .map_err(|err| Error::Database(DatabaseError::from_source(err)))
+}
+
+impl Pool {
+    /// Build a pool with the default options
+    pub fn new(url: &str) -> Result<Self, Error> {
+        Pool::builder().build(url)
+    }
+
+    pub fn builder() -> PoolBuilder {
+        PoolBuilder::default()
+    }
+
+    /// Check out a connection, which is returned to the pool when dropped. Waits up to the
+    /// checkout timeout for a connection when all of them are in use.
+    pub fn get(&self) -> Result<PooledConnection, Error> {
+        let deadline = Instant::now() + self.shared.options.checkout_timeout;
+        let mut state = self.shared.lock();
+        loop {
+            let expired = self.shared.evict_expired(&mut state);
+            if !expired.is_empty() {
+                drop(state);
+                drop(expired);
+                state = self.shared.lock();
+                continue;
+            }
+
+            if let Some(idle) = state.idle.pop_back() {
+                drop(state);
+                let mut database = idle.database;
+                if self.shared.options.test_on_checkout && database.ping().is_err() {
+                    drop(database);
+                    state = self.shared.lock();
+                    self.shared.release_slot(&mut state);
+                    continue;
+                }
+                return Ok(self.guard(database));
+            }
+
+            if state.size < self.shared.options.max_size {
+                state.size += 1;
+                drop(state);
+                return match Database::new(&self.shared.url) {
+                    Ok(database) => Ok(self.guard(database)),
+                    Err(err) => {
+                        self.shared.release_slot(&mut self.shared.lock());
+                        Err(err)
+                    }
+                };
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(Error::Timeout(DatabaseError::new(
+                    "Timed out waiting for a connection from the pool",
+                )));
+            }
+            state = self
+                .shared
+                .available
+                .wait_timeout(state, deadline - now)
+                .unwrap_or_else(|err| err.into_inner())
+                .0;
+        }
+    }
+
+    /// The number of open connections, both idle and checked out
+    pub fn size(&self) -> usize {
+        self.shared.lock().size
+    }
+
+    pub fn idle_count(&self) -> usize {
+        self.shared.lock().idle.len()
+    }
+
+    fn guard(&self, database: Database) -> PooledConnection {
+        PooledConnection {
+            database: Some(database),
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // The state is kept consistent across panics, so a poisoned lock is still usable
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Take the connections which have been idle for too long out of the pool, so that they
+    /// can be closed without holding the lock
+    fn evict_expired(&self, state: &mut State) -> Vec<IdleConnection> {
+        let Some(idle_timeout) = self.options.idle_timeout else {
+            return Vec::new();
+        };
+        let mut expired = Vec::new();
+        while state.size > self.options.min_size {
+            match state.idle.front() {
+                Some(idle) if idle.idle_since.elapsed() >= idle_timeout => {
+                    expired.extend(state.idle.pop_front());
+                    state.size -= 1;
+                }
+                _ => break,
+            }
+        }
+        expired
+    }
+
+    fn release_slot(&self, state: &mut State) {
+        state.size -= 1;
+        self.available.notify_one();
+    }
+}
+
+/// A connection checked out of a pool, returned to it when dropped
+pub struct PooledConnection {
+    database: Option<Database>,
+    shared: Arc<Shared>,
+}
+
+impl Deref for PooledConnection {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        self.database.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledConnection {
+    fn deref_mut(&mut self) -> &mut Database {
+        self.database.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        if let Some(database) = self.database.take() {
+            self.shared.lock().idle.push_back(IdleConnection {
+                database,
+                idle_since: Instant::now(),
+            });
+            self.shared.available.notify_one();
+        }
+    }
+}
@@ -1 +1,827 @@
-use postgres;
\ No newline at end of file
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::BytesMut;
+use postgres;
+use postgres::config::SslMode;
+use postgres::fallible_iterator::FallibleIterator;
+use postgres::types::{to_sql_checked, Format, FromSql, IsNull, Kind, ToSql, Type};
+use postgres::GenericClient;
+use sqlparser::dialect::{Dialect, PostgreSqlDialect};
+
+use crate::dbc;
+
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub(crate) struct PostgresConnection {
+    connection: postgres::Client,
+}
+
+impl PostgresConnection {
+    pub(crate) fn get_connection(
+        options: &dbc::PostgresOptions,
+    ) -> Result<Box<dyn dbc::Connection + Send>, dbc::Error> {
+        // Parameters without a typed option are parsed as a key=value connection string
+        let mut config = options
+            .params
+            .iter()
+            .map(|(name, value)| {
+                let value = value.replace('\\', "\\\\").replace('\'', "\\'");
+                format!("{}='{}'", name, value)
+            })
+            .collect::<Vec<String>>()
+            .join(" ")
+            .parse::<postgres::Config>()
+            .map_err(|err| dbc::Error::Configuration(err.to_string()))?;
+        config.host(&options.host).port(options.port);
+        if let Some(user) = &options.user {
+            config.user(user);
+        }
+        if let Some(password) = &options.password {
+            config.password(password);
+        }
+        if let Some(database) = &options.database {
+            config.dbname(database);
+        }
+        if let Some(connect_timeout) = options.connect_timeout {
+            config.connect_timeout(connect_timeout);
+        }
+        if let Some(ssl_mode) = options.ssl_mode {
+            config.ssl_mode(match ssl_mode {
+                dbc::PostgresSslMode::Disable => SslMode::Disable,
+                dbc::PostgresSslMode::Prefer => SslMode::Prefer,
+                dbc::PostgresSslMode::Require => SslMode::Require,
+            });
+        }
+        let connection =
+            config
+                .connect(postgres::NoTls)
+                .map_err(|err| match dbc::Error::from(err) {
+                    // Errors without a SQLSTATE happen before the server could answer
+                    dbc::Error::Database(error) => dbc::Error::Connection(error),
+                    err => err,
+                })?;
+        Ok(Box::new(PostgresConnection { connection }) as Box<dyn dbc::Connection + Send>)
+    }
+}
+
+impl dbc::Connection for PostgresConnection {
+    fn execute_with_params(
+        &mut self,
+        query: &str,
+        params: &[dbc::Value],
+    ) -> Result<dbc::QueryResult, dbc::Error> {
+        prepare(&mut self.connection, query, !params.is_empty())?.execute(params)
+    }
+
+    fn prepare(&mut self, query: &str) -> Result<Box<dyn dbc::Statement + '_>, dbc::Error> {
+        prepare(&mut self.connection, query, true)
+    }
+
+    fn query_iter(
+        &mut self,
+        query: &str,
+        params: &[dbc::Value],
+    ) -> Result<dbc::RowCursor<'_>, dbc::Error> {
+        let (statement, columns) =
+            prepare_statement(&mut self.connection, query, !params.is_empty())?;
+        query_raw(&mut self.connection, &statement, params, columns)
+    }
+
+    fn transaction(&mut self) -> Result<Box<dyn dbc::TransactionConnection + '_>, dbc::Error> {
+        Ok(Box::new(PostgresTransaction {
+            transaction: self.connection.transaction()?,
+        }))
+    }
+
+    fn dialect(&self) -> &'static dyn Dialect {
+        &PostgreSqlDialect {}
+    }
+
+    fn ping(&mut self) -> Result<(), dbc::Error> {
+        Ok(self.connection.is_valid(PING_TIMEOUT)?)
+    }
+}
+
+pub(crate) struct PostgresTransaction<'a> {
+    transaction: postgres::Transaction<'a>,
+}
+
+impl dbc::Connection for PostgresTransaction<'_> {
+    fn execute_with_params(
+        &mut self,
+        query: &str,
+        params: &[dbc::Value],
+    ) -> Result<dbc::QueryResult, dbc::Error> {
+        prepare(&mut self.transaction, query, !params.is_empty())?.execute(params)
+    }
+
+    fn prepare(&mut self, query: &str) -> Result<Box<dyn dbc::Statement + '_>, dbc::Error> {
+        prepare(&mut self.transaction, query, true)
+    }
+
+    fn query_iter(
+        &mut self,
+        query: &str,
+        params: &[dbc::Value],
+    ) -> Result<dbc::RowCursor<'_>, dbc::Error> {
+        let (statement, columns) =
+            prepare_statement(&mut self.transaction, query, !params.is_empty())?;
+        query_raw(&mut self.transaction, &statement, params, columns)
+    }
+
+    fn transaction(&mut self) -> Result<Box<dyn dbc::TransactionConnection + '_>, dbc::Error> {
+        Err(dbc::Error::Usage("Transactions cannot be nested".into()))
+    }
+
+    fn dialect(&self) -> &'static dyn Dialect {
+        &PostgreSqlDialect {}
+    }
+}
+
+impl dbc::TransactionConnection for PostgresTransaction<'_> {
+    fn commit(self: Box<Self>) -> Result<(), dbc::Error> {
+        Ok(self.transaction.commit()?)
+    }
+
+    fn rollback(self: Box<Self>) -> Result<(), dbc::Error> {
+        Ok(self.transaction.rollback()?)
+    }
+
+    fn savepoint(
+        &mut self,
+        name: &str,
+    ) -> Result<Box<dyn dbc::TransactionConnection + '_>, dbc::Error> {
+        // A savepoint is a nested postgres::Transaction, releasing it on commit
+        Ok(Box::new(PostgresTransaction {
+            transaction: self.transaction.savepoint(name)?,
+        }))
+    }
+}
+
+fn prepare<'a, C: GenericClient>(
+    connection: &'a mut C,
+    query: &str,
+    has_params: bool,
+) -> Result<Box<dyn dbc::Statement + 'a>, dbc::Error> {
+    let (statement, columns) = prepare_statement(connection, query, has_params)?;
+    Ok(Box::new(PostgresStatement {
+        connection,
+        statement,
+        columns,
+        kind: dbc::sql::statement_kind(&PostgreSqlDialect {}, query),
+    }))
+}
+
+/// Prepare a statement, numbering its `?` placeholders if it may have parameters. Queries
+/// without parameters are sent unchanged, as `?` is also a jsonb operator.
+fn prepare_statement<C: GenericClient>(
+    connection: &mut C,
+    query: &str,
+    has_params: bool,
+) -> Result<(postgres::Statement, Arc<[dbc::Column]>), dbc::Error> {
+    let statement = if has_params {
+        connection.prepare(&dbc::sql::number_placeholders(
+            &PostgreSqlDialect {},
+            query,
+        )?)?
+    } else {
+        connection.prepare(query)?
+    };
+    let columns = statement
+        .columns()
+        .iter()
+        .map(|column| dbc::Column {
+            name: column.name().to_string(),
+            column_type: column.type_().into(),
+        })
+        .collect();
+    Ok((statement, columns))
+}
+
+/// Execute a statement, streaming its rows as they are received from the server
+fn query_raw<'a, C: GenericClient>(
+    connection: &'a mut C,
+    statement: &postgres::Statement,
+    params: &[dbc::Value],
+    columns: Arc<[dbc::Column]>,
+) -> Result<dbc::RowCursor<'a>, dbc::Error> {
+    let rows = connection.query_raw(statement, params)?;
+    let row_columns = Arc::clone(&columns);
+    let rows = rows.iterator().map(move |row| {
+        let row = row?;
+        let mut values: Vec<dbc::Value> = Vec::with_capacity(row.len());
+        for (i, column) in row.columns().iter().enumerate() {
+            let raw: Option<RawValue> = row.try_get(i)?;
+            values.push(match raw {
+                Some(raw) => decode_value(column.type_(), raw.0).map_err(|err| {
+                    dbc::Error::TypeConversion(dbc::DatabaseError::from_source(err))
+                })?,
+                None => dbc::Value::NULL,
+            });
+        }
+        Ok(dbc::Row {
+            values,
+            columns: Arc::clone(&row_columns),
+        })
+    });
+    Ok(dbc::RowCursor::new(columns, rows))
+}
+
+pub(crate) struct PostgresStatement<'a, C: GenericClient> {
+    connection: &'a mut C,
+    statement: postgres::Statement,
+    columns: Arc<[dbc::Column]>,
+    kind: dbc::StatementKind,
+}
+
+impl<C: GenericClient> dbc::Statement for PostgresStatement<'_, C> {
+    fn execute(&mut self, params: &[dbc::Value]) -> Result<dbc::QueryResult, dbc::Error> {
+        if self.columns.is_empty() {
+            let params = params
+                .iter()
+                .map(|param| param as &(dyn ToSql + Sync))
+                .collect::<Vec<_>>();
+            let affected_rows = self.connection.execute(&self.statement, &params)?;
+            return Ok(dbc::QueryResult {
+                rows: Vec::new(),
+                columns: Arc::clone(&self.columns),
+                affected_row_count: affected_rows as usize,
+                kind: self.kind,
+            });
+        }
+
+        let kind = self.kind;
+        Ok(dbc::QueryResult::try_from(self.query(params)?)?.with_kind(kind))
+    }
+
+    fn query(&mut self, params: &[dbc::Value]) -> Result<dbc::RowCursor<'_>, dbc::Error> {
+        query_raw(
+            self.connection,
+            &self.statement,
+            params,
+            Arc::clone(&self.columns),
+        )
+    }
+
+    fn parameter_count(&self) -> usize {
+        self.statement.params().len()
+    }
+
+    fn columns(&self) -> &[dbc::Column] {
+        &self.columns
+    }
+}
+
+/// Parameters are sent in the text format, leaving it to the server to parse them as the type
+/// it inferred for the placeholder. Only blobs bound to bytea placeholders are sent as binary.
+impl ToSql for dbc::Value {
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        match (self, self.encode_format(ty)) {
+            (dbc::Value::NULL, _) => return Ok(IsNull::Yes),
+            (dbc::Value::Bytes(bytes), Format::Binary) => out.extend_from_slice(bytes),
+            // Parameters of other types are sent as text, which must be valid UTF-8
+            (dbc::Value::Bytes(bytes), Format::Text) => {
+                let text = std::str::from_utf8(bytes)
+                    .map_err(|err| format!("Cannot bind bytes to a {} parameter: {}", ty, err))?;
+                out.extend_from_slice(text.as_bytes())
+            }
+            (value, _) => match value.to_sql_text() {
+                Some(text) => out.extend_from_slice(text.as_bytes()),
+                None => return Ok(IsNull::Yes),
+            },
+        }
+        Ok(IsNull::No)
+    }
+
+    fn accepts(_: &Type) -> bool {
+        true
+    }
+
+    fn encode_format(&self, ty: &Type) -> Format {
+        match (self, ty) {
+            (dbc::Value::Bytes(_), &Type::BYTEA) => Format::Binary,
+            _ => Format::Text,
+        }
+    }
+
+    to_sql_checked!();
+}
+
+/// The binary representation of a value as sent by the server, decoded by `decode_value`
+struct RawValue<'a>(&'a [u8]);
+
+impl From<postgres::Error> for dbc::Error {
+    fn from(err: postgres::Error) -> Self {
+        // Server errors are displayed as "db error" by the postgres crate, their message is
+        // given as psql does instead
+        if let Some(db_error) = err.as_db_error() {
+            let mut message = db_error.message().to_owned();
+            if let Some(detail) = db_error.detail() {
+                message.push_str(&format!("\nDETAIL: {}", detail));
+            }
+            if let Some(hint) = db_error.hint() {
+                message.push_str(&format!("\nHINT: {}", hint));
+            }
+            let sql_state = db_error.code().code().to_owned();
+            return dbc::Error::from_sql_state(
+                dbc::DatabaseError::new(message)
+                    .with_sql_state(sql_state)
+                    .with_source(err),
+            );
+        }
+        let is_io_error =
+            std::error::Error::source(&err).is_some_and(|source| source.is::<std::io::Error>());
+        if err.is_closed() || is_io_error {
+            dbc::Error::Connection(dbc::DatabaseError::from_source(err))
+        } else {
+            dbc::Error::Database(dbc::DatabaseError::from_source(err))
+        }
+    }
+}
+
+impl<'a> FromSql<'a> for RawValue<'a> {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        Ok(RawValue(raw))
+    }
+
+    fn accepts(_: &Type) -> bool {
+        true
+    }
+}
+
+/// The errors of the FromSql implementations, which are turned into dbc::Error::TypeConversion
+type DecodeError = Box<dyn std::error::Error + Sync + Send>;
+
+/// Days between the Unix epoch and the Postgres epoch (2000-01-01)
+const POSTGRES_EPOCH_DAYS: i64 = 10_957;
+const MICROSECONDS_PER_DAY: i64 = 86_400_000_000;
+
+fn decode_value(ty: &Type, raw: &[u8]) -> Result<dbc::Value, DecodeError> {
+    let value = match *ty {
+        Type::BOOL => dbc::Value::Bool(bool::from_sql(ty, raw)?),
+        Type::CHAR => dbc::Value::Int(i8::from_sql(ty, raw)? as i64),
+        Type::INT2 => dbc::Value::Int(i16::from_sql(ty, raw)? as i64),
+        Type::INT4 => dbc::Value::Int(i32::from_sql(ty, raw)? as i64),
+        Type::INT8 => dbc::Value::Int(i64::from_sql(ty, raw)?),
+        Type::OID => dbc::Value::UInt(u32::from_sql(ty, raw)? as u64),
+        // money is sent as an integer amount of cents, read as a decimal text like NUMERIC
+        Type::MONEY => dbc::Value::String(decode_money(i64::from_sql(&Type::INT8, raw)?)),
+        Type::FLOAT4 => dbc::Value::Float(f32::from_sql(ty, raw)?),
+        Type::FLOAT8 => dbc::Value::Double(f64::from_sql(ty, raw)?),
+        Type::NUMERIC => dbc::Value::String(decode_numeric(raw)?),
+        Type::JSONB => match raw.split_first() {
+            // jsonb is prefixed with a format version, only version 1 exists
+            Some((1, json)) => dbc::Value::from_text(json.to_vec()),
+            _ => return Err("Unsupported jsonb format version".into()),
+        },
+        Type::DATE => match i32::from_sql(ty, raw)? {
+            i32::MAX => dbc::Value::String("infinity".to_owned()),
+            i32::MIN => dbc::Value::String("-infinity".to_owned()),
+            days => decode_timestamp(days as i64 * MICROSECONDS_PER_DAY)?,
+        },
+        Type::TIMESTAMP | Type::TIMESTAMPTZ => match i64::from_sql(ty, raw)? {
+            i64::MAX => dbc::Value::String("infinity".to_owned()),
+            i64::MIN => dbc::Value::String("-infinity".to_owned()),
+            microseconds => decode_timestamp(microseconds)?,
+        },
+        Type::TIME => decode_interval(i64::from_sql(&Type::INT8, raw)?, 0, 0)?,
+        // The zone offset of timetz is not representable in dbc::Value::Time, it is read as text
+        Type::TIMETZ => dbc::Value::String(decode_timetz(raw)?),
+        Type::INTERVAL => {
+            let microseconds = i64::from_sql(&Type::INT8, read_bytes(raw, 0, 8)?)?;
+            let days = i32::from_sql(&Type::INT4, read_bytes(raw, 8, 4)?)?;
+            let months = i32::from_sql(&Type::INT4, read_bytes(raw, 12, 4)?)?;
+            decode_interval(microseconds, days, months)?
+        }
+        Type::UUID => {
+            let uuid = read_bytes(raw, 0, 16)?
+                .iter()
+                .enumerate()
+                .map(|(i, byte)| match i {
+                    4 | 6 | 8 | 10 => format!("-{:02x}", byte),
+                    _ => format!("{:02x}", byte),
+                })
+                .collect::<String>();
+            dbc::Value::String(uuid)
+        }
+        Type::INET | Type::CIDR => dbc::Value::String(decode_inet(ty, raw)?),
+        Type::MACADDR | Type::MACADDR8 => dbc::Value::String(
+            raw.iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<Vec<_>>()
+                .join(":"),
+        ),
+        // Bit strings are sent as their length in bits followed by the bits, rendered as 0s and 1s
+        Type::BIT | Type::VARBIT => {
+            let length = i32::from_sql(&Type::INT4, read_bytes(raw, 0, 4)?)?;
+            let bits = (0..length.max(0) as usize)
+                .map(
+                    |i| match read_bytes(raw, 4 + i / 8, 1)?[0] & (0x80 >> (i % 8)) {
+                        0 => Ok('0'),
+                        _ => Ok('1'),
+                    },
+                )
+                .collect::<Result<String, DecodeError>>()?;
+            dbc::Value::String(bits)
+        }
+        // Geometric types are sent as their coordinates, rendered in the PostgreSQL text form
+        Type::POINT => dbc::Value::String(decode_points(raw, 0, 1)?.concat()),
+        Type::LINE => {
+            let [a, b, c] = [0, 8, 16].map(|offset| decode_float(raw, offset));
+            dbc::Value::String(format!("{{{},{},{}}}", a?, b?, c?))
+        }
+        Type::LSEG => dbc::Value::String(format!("[{}]", decode_points(raw, 0, 2)?.join(","))),
+        Type::BOX => dbc::Value::String(decode_points(raw, 0, 2)?.join(",")),
+        Type::PATH => {
+            let is_closed = read_bytes(raw, 0, 1)?[0] != 0;
+            let count = i32::from_sql(&Type::INT4, read_bytes(raw, 1, 4)?)?;
+            let points = decode_points(raw, 5, count)?.join(",");
+            dbc::Value::String(match is_closed {
+                true => format!("({})", points),
+                false => format!("[{}]", points),
+            })
+        }
+        Type::POLYGON => {
+            let count = i32::from_sql(&Type::INT4, read_bytes(raw, 0, 4)?)?;
+            dbc::Value::String(format!("({})", decode_points(raw, 4, count)?.join(",")))
+        }
+        Type::CIRCLE => dbc::Value::String(format!(
+            "<{},{}>",
+            decode_points(raw, 0, 1)?.concat(),
+            decode_float(raw, 16)?
+        )),
+        Type::BYTEA => dbc::Value::Bytes(raw.to_vec()),
+        Type::VOID => dbc::Value::NULL,
+        // Text-like types are sent as their UTF-8 text
+        Type::TEXT
+        | Type::VARCHAR
+        | Type::BPCHAR
+        | Type::NAME
+        | Type::JSON
+        | Type::XML
+        | Type::UNKNOWN => dbc::Value::from_text(raw.to_vec()),
+        _ => match ty.kind() {
+            Kind::Domain(inner) => decode_value(inner, raw)?,
+            Kind::Enum(_) => dbc::Value::from_text(raw.to_vec()),
+            Kind::Array(element_type) => dbc::Value::String(decode_array(element_type, raw)?),
+            Kind::Range(subtype) => dbc::Value::String(decode_range(subtype, raw)?),
+            // Other types, e.g. tsvector, are read as their binary encoding rather than failing
+            // the whole query
+            _ => dbc::Value::Bytes(raw.to_vec()),
+        },
+    };
+    Ok(value)
+}
+
+/// Renders an array in the PostgreSQL text form, e.g. `{1,2,NULL}` or `{{a,b},{c,d}}`
+fn decode_array(element_type: &Type, raw: &[u8]) -> Result<String, DecodeError> {
+    let header = |offset| i32::from_sql(&Type::INT4, read_bytes(raw, offset, 4)?);
+    // The header is the number of dimensions, a has-nulls flag and the element type OID,
+    // followed by the length and lower bound of each dimension
+    let dimensions = usize::try_from(header(0)?).map_err(|_| "Invalid array dimensions")?;
+    if dimensions == 0 {
+        return Ok("{}".to_owned());
+    }
+    let lengths = (0..dimensions)
+        .map(|i| usize::try_from(header(12 + i * 8)?).map_err(|_| "Invalid array length".into()))
+        .collect::<Result<Vec<usize>, DecodeError>>()?;
+
+    let mut offset = 12 + dimensions * 8;
+    let mut elements = Vec::new();
+    for _ in 0..lengths.iter().product::<usize>() {
+        let length = header(offset)?;
+        offset += 4;
+        // A negative length marks a NULL element
+        let Ok(length) = usize::try_from(length) else {
+            elements.push("NULL".to_owned());
+            continue;
+        };
+        let value = decode_value(element_type, read_bytes(raw, offset, length)?)?;
+        offset += length;
+        elements.push(element_text(&value, "{},\"\\"));
+    }
+    Ok(nest_array(&lengths, &mut elements.into_iter()))
+}
+
+fn nest_array(lengths: &[usize], elements: &mut impl Iterator<Item = String>) -> String {
+    let Some((length, inner_lengths)) = lengths.split_first() else {
+        return elements.next().unwrap_or_default();
+    };
+    let items = (0..*length)
+        .map(|_| nest_array(inner_lengths, elements))
+        .collect::<Vec<_>>();
+    format!("{{{}}}", items.join(","))
+}
+
+/// Renders a range in the PostgreSQL text form, e.g. `[1,5)`, `(,2.5]` or `empty`
+fn decode_range(subtype: &Type, raw: &[u8]) -> Result<String, DecodeError> {
+    const EMPTY: u8 = 0x01;
+    const LOWER_INCLUSIVE: u8 = 0x02;
+    const UPPER_INCLUSIVE: u8 = 0x04;
+    const LOWER_INFINITE: u8 = 0x08;
+    const UPPER_INFINITE: u8 = 0x10;
+
+    let flags = read_bytes(raw, 0, 1)?[0];
+    if flags & EMPTY != 0 {
+        return Ok("empty".to_owned());
+    }
+    // Each finite bound is sent as its length followed by its value
+    let mut offset = 1;
+    let mut bound = |is_infinite: bool| -> Result<String, DecodeError> {
+        if is_infinite {
+            return Ok(String::new());
+        }
+        let length = i32::from_sql(&Type::INT4, read_bytes(raw, offset, 4)?)?;
+        let length = usize::try_from(length).map_err(|_| "Invalid range bound length")?;
+        let value = decode_value(subtype, read_bytes(raw, offset + 4, length)?)?;
+        offset += 4 + length;
+        Ok(element_text(&value, "()[],\"\\"))
+    };
+    let lower = bound(flags & LOWER_INFINITE != 0)?;
+    let upper = bound(flags & UPPER_INFINITE != 0)?;
+    Ok(format!(
+        "{}{},{}{}",
+        if flags & LOWER_INCLUSIVE != 0 {
+            '['
+        } else {
+            '('
+        },
+        lower,
+        upper,
+        if flags & UPPER_INCLUSIVE != 0 {
+            ']'
+        } else {
+            ')'
+        },
+    ))
+}
+
+/// The text of an array element or range bound, quoted when it is empty, `NULL` or contains
+/// whitespace or one of the `special` characters
+fn element_text(value: &dbc::Value, special: &str) -> String {
+    let text = match value {
+        dbc::Value::Bool(bool) => if *bool { "t" } else { "f" }.to_owned(),
+        dbc::Value::Bytes(bytes) => format!(
+            "\\x{}",
+            bytes
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<String>()
+        ),
+        value => match value.to_sql_text() {
+            Some(text) => text,
+            None => return "NULL".to_owned(),
+        },
+    };
+    let needs_quotes = text.is_empty()
+        || text.eq_ignore_ascii_case("NULL")
+        || text
+            .chars()
+            .any(|c| special.contains(c) || c.is_whitespace());
+    if !needs_quotes {
+        return text;
+    }
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Renders an inet or cidr address, with its prefix length unless an inet is a single host
+fn decode_inet(ty: &Type, raw: &[u8]) -> Result<String, DecodeError> {
+    let header = read_bytes(raw, 0, 4)?;
+    let (family, bits, length) = (header[0], header[1], header[3]);
+    let address = read_bytes(raw, 4, length as usize)?;
+    let (address, max_bits) = match family {
+        // PGSQL_AF_INET and PGSQL_AF_INET6
+        2 => (
+            std::net::Ipv4Addr::from(<[u8; 4]>::try_from(address)?).to_string(),
+            32,
+        ),
+        3 => (
+            std::net::Ipv6Addr::from(<[u8; 16]>::try_from(address)?).to_string(),
+            128,
+        ),
+        _ => return Err(format!("Invalid inet address family {}", family).into()),
+    };
+    if *ty == Type::CIDR || bits != max_bits {
+        Ok(format!("{}/{}", address, bits))
+    } else {
+        Ok(address)
+    }
+}
+
+/// Renders an amount of cents as a decimal, e.g. `-12.34`. The fractional digits of money
+/// depend on `lc_monetary`, two in most locales.
+fn decode_money(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let cents = cents.unsigned_abs();
+    format!("{}{}.{:02}", sign, cents / 100, cents % 100)
+}
+
+/// Renders a time with its zone offset, e.g. `13:45:30.25+05:30`
+fn decode_timetz(raw: &[u8]) -> Result<String, DecodeError> {
+    let microseconds = i64::from_sql(&Type::INT8, read_bytes(raw, 0, 8)?)?;
+    // The offset is sent in seconds west of UTC, while its text is east of UTC
+    let offset = -i32::from_sql(&Type::INT4, read_bytes(raw, 8, 4)?)?;
+    let seconds = microseconds / 1_000_000;
+    let mut text = format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    );
+    let fraction = microseconds % 1_000_000;
+    if fraction != 0 {
+        text.push_str(format!(".{:06}", fraction).trim_end_matches('0'));
+    }
+    let sign = if offset < 0 { '-' } else { '+' };
+    let offset = offset.unsigned_abs();
+    text.push_str(&format!("{}{:02}", sign, offset / 3600));
+    if offset % 3600 != 0 {
+        text.push_str(&format!(":{:02}", offset / 60 % 60));
+    }
+    if offset % 60 != 0 {
+        text.push_str(&format!(":{:02}", offset % 60));
+    }
+    Ok(text)
+}
+
+fn decode_float(raw: &[u8], offset: usize) -> Result<f64, DecodeError> {
+    f64::from_sql(&Type::FLOAT8, read_bytes(raw, offset, 8)?)
+}
+
+/// Renders `count` points starting at `offset` as `(x,y)`
+fn decode_points(raw: &[u8], offset: usize, count: i32) -> Result<Vec<String>, DecodeError> {
+    let count = usize::try_from(count).map_err(|_| "Invalid point count")?;
+    (0..count)
+        .map(|i| {
+            let x = decode_float(raw, offset + i * 16)?;
+            let y = decode_float(raw, offset + i * 16 + 8)?;
+            Ok(format!("({},{})", x, y))
+        })
+        .collect()
+}
+
+fn read_bytes(raw: &[u8], offset: usize, len: usize) -> Result<&[u8], DecodeError> {
+    raw.get(offset..offset + len)
+        .ok_or_else(|| "Unexpected end of binary value".into())
+}
+
+/// Converts microseconds since the Postgres epoch into a dbc::Value::Date
+fn decode_timestamp(microseconds: i64) -> Result<dbc::Value, DecodeError> {
+    let days = microseconds.div_euclid(MICROSECONDS_PER_DAY) + POSTGRES_EPOCH_DAYS;
+    let time = microseconds.rem_euclid(MICROSECONDS_PER_DAY);
+
+    // Days since the Unix epoch to a civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    let year = u16::try_from(year).map_err(|_| format!("Year {} is out of range", year))?;
+
+    Ok(dbc::Value::Date(
+        year,
+        month as u8,
+        day as u8,
+        (time / 3_600_000_000) as u8,
+        (time / 60_000_000 % 60) as u8,
+        (time / 1_000_000 % 60) as u8,
+        (time % 1_000_000) as u32,
+    ))
+}
+
+/// Converts a Postgres interval into a dbc::Value::Time, a month is counted as 30 days
+fn decode_interval(microseconds: i64, days: i32, months: i32) -> Result<dbc::Value, DecodeError> {
+    let total = microseconds + (days as i64 + months as i64 * 30) * MICROSECONDS_PER_DAY;
+    let is_negative = total < 0;
+    let total = total.unsigned_abs();
+    let days = u32::try_from(total / MICROSECONDS_PER_DAY as u64)
+        .map_err(|_| "Interval is out of range")?;
+    let time = total % MICROSECONDS_PER_DAY as u64;
+    Ok(dbc::Value::Time(
+        is_negative,
+        days,
+        (time / 3_600_000_000) as u8,
+        (time / 60_000_000 % 60) as u8,
+        (time / 1_000_000 % 60) as u8,
+        (time % 1_000_000) as u32,
+    ))
+}
+
+/// Renders the binary numeric format (base 10000 digits) as a decimal string
+fn decode_numeric(raw: &[u8]) -> Result<String, DecodeError> {
+    let header = |offset| i16::from_sql(&Type::INT2, read_bytes(raw, offset, 2)?);
+    let num_digits = header(0)?;
+    let weight = header(2)?;
+    let sign = header(4)? as u16;
+    let scale = header(6)? as u16 as usize;
+    let digits = (0..num_digits as usize)
+        .map(|i| header(8 + i * 2))
+        .collect::<Result<Vec<i16>, _>>()?;
+
+    match sign {
+        0x0000 | 0x4000 => {}
+        0xC000 => return Ok("NaN".to_string()),
+        0xD000 => return Ok("Infinity".to_string()),
+        0xF000 => return Ok("-Infinity".to_string()),
+        _ => return Err("Invalid numeric sign".into()),
+    }
+
+    let digit = |index: i32| {
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| digits.get(index).copied())
+            .unwrap_or(0)
+    };
+
+    let mut decimal = String::new();
+    if sign == 0x4000 {
+        decimal.push('-');
+    }
+    if weight < 0 {
+        decimal.push('0');
+    } else {
+        decimal.push_str(&digit(0).to_string());
+        for i in 1..=weight as i32 {
+            decimal.push_str(&format!("{:04}", digit(i)));
+        }
+    }
+    if scale > 0 {
+        let mut fraction = String::new();
+        let mut i = weight as i32 + 1;
+        while fraction.len() < scale {
+            fraction.push_str(&format!("{:04}", digit(i)));
+            i += 1;
+        }
+        fraction.truncate(scale);
+        decimal.push('.');
+        decimal.push_str(&fraction);
+    }
+    Ok(decimal)
+}
+
+impl From<&Type> for dbc::ColumnType {
+    fn from(postgres_type: &Type) -> Self {
+        match *postgres_type {
+            Type::BOOL => dbc::ColumnType::BIT,
+            Type::CHAR => dbc::ColumnType::INT,
+            Type::INT2 => dbc::ColumnType::INT,
+            Type::INT4 => dbc::ColumnType::INT,
+            Type::INT8 => dbc::ColumnType::INT,
+            Type::OID => dbc::ColumnType::INT,
+            Type::FLOAT4 => dbc::ColumnType::FLOAT,
+            Type::FLOAT8 => dbc::ColumnType::DOUBLE,
+            Type::NUMERIC => dbc::ColumnType::DECIMAL,
+            Type::MONEY => dbc::ColumnType::DECIMAL,
+            Type::TEXT => dbc::ColumnType::VARCHAR,
+            Type::VARCHAR => dbc::ColumnType::VARCHAR,
+            Type::NAME => dbc::ColumnType::VARCHAR,
+            Type::BPCHAR => dbc::ColumnType::STRING,
+            Type::UUID => dbc::ColumnType::STRING,
+            Type::XML => dbc::ColumnType::STRING,
+            Type::INET => dbc::ColumnType::STRING,
+            Type::CIDR => dbc::ColumnType::STRING,
+            Type::MACADDR => dbc::ColumnType::STRING,
+            Type::MACADDR8 => dbc::ColumnType::STRING,
+            Type::BYTEA => dbc::ColumnType::BLOB,
+            Type::DATE => dbc::ColumnType::DATE,
+            Type::TIME => dbc::ColumnType::TIME,
+            Type::TIMETZ => dbc::ColumnType::STRING,
+            Type::INTERVAL => dbc::ColumnType::TIME,
+            Type::TIMESTAMP => dbc::ColumnType::DATETIME,
+            Type::TIMESTAMPTZ => dbc::ColumnType::TIMESTAMP,
+            Type::JSON => dbc::ColumnType::JSON,
+            Type::JSONB => dbc::ColumnType::JSON,
+            Type::BIT => dbc::ColumnType::BIT,
+            Type::VARBIT => dbc::ColumnType::BIT,
+            Type::POINT => dbc::ColumnType::GEOMETRY,
+            Type::LINE => dbc::ColumnType::GEOMETRY,
+            Type::LSEG => dbc::ColumnType::GEOMETRY,
+            Type::BOX => dbc::ColumnType::GEOMETRY,
+            Type::PATH => dbc::ColumnType::GEOMETRY,
+            Type::POLYGON => dbc::ColumnType::GEOMETRY,
+            Type::CIRCLE => dbc::ColumnType::GEOMETRY,
+            Type::VOID => dbc::ColumnType::NULL,
+            _ => match postgres_type.kind() {
+                Kind::Enum(_) => dbc::ColumnType::ENUM,
+                Kind::Domain(inner) => inner.into(),
+                // Arrays and ranges are read as their text form
+                Kind::Array(_) | Kind::Range(_) => dbc::ColumnType::STRING,
+                _ => dbc::ColumnType::UNKNOWN, // Create an issue or PR if you need more type support
+            },
+        }
+    }
+}
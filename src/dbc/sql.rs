@@ -0,0 +1,267 @@
+use sqlparser::dialect::Dialect;
+use sqlparser::tokenizer::{Location, Token, Tokenizer};
+
+use crate::dbc;
+
+/// A token together with its byte range in the tokenized query
+pub(crate) struct SpannedToken {
+    pub(crate) token: Token,
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+}
+
+/// Tokenize a query, keeping track of where each token is located in the original text so
+/// that the query can be rewritten without touching string literals, identifiers or comments
+pub(crate) fn tokenize(
+    dialect: &dyn Dialect,
+    query: &str,
+) -> Result<Vec<SpannedToken>, dbc::Error> {
+    let tokens = Tokenizer::new(dialect, query)
+        .tokenize_with_location()
+        .map_err(|err| dbc::Error::Syntax(dbc::DatabaseError::from_source(err)))?;
+
+    let line_starts = std::iter::once(0)
+        .chain(query.match_indices('\n').map(|(i, _)| i + 1))
+        .collect::<Vec<usize>>();
+    let offset = |location: &Location| {
+        let line_start = line_starts[location.line as usize - 1];
+        query[line_start..]
+            .char_indices()
+            .nth(location.column as usize - 1)
+            .map_or(query.len(), |(i, _)| line_start + i)
+    };
+
+    let starts = tokens
+        .iter()
+        .map(|token| offset(&token.location))
+        .collect::<Vec<usize>>();
+    Ok(tokens
+        .into_iter()
+        .enumerate()
+        .map(|(i, token)| SpannedToken {
+            token: token.token,
+            start: starts[i],
+            end: starts.get(i + 1).copied().unwrap_or(query.len()),
+        })
+        .collect())
+}
+
+/// Rewrite `?` placeholders into the numbered `$1, $2, ...` style, leaving queries that
+/// already use numbered placeholders untouched
+#[cfg(feature = "postgres")]
+pub(crate) fn number_placeholders(
+    dialect: &dyn Dialect,
+    query: &str,
+) -> Result<String, dbc::Error> {
+    let tokens = tokenize(dialect, query)?;
+    let is_numbered = |placeholder: &str| {
+        placeholder
+            .strip_prefix('$')
+            .is_some_and(|number| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()))
+    };
+    if tokens.iter().any(
+        |token| matches!(&token.token, Token::Placeholder(placeholder) if is_numbered(placeholder)),
+    ) {
+        return Ok(query.to_owned());
+    }
+
+    let mut numbered = String::with_capacity(query.len());
+    let mut last_end = 0;
+    let mut count = 0;
+    for token in tokens {
+        if let Token::Placeholder(placeholder) = &token.token {
+            if placeholder == "?" {
+                count += 1;
+                numbered.push_str(&query[last_end..token.start]);
+                numbered.push_str(&format!("${}", count));
+                last_end = token.end;
+            }
+        }
+    }
+    numbered.push_str(&query[last_end..]);
+    Ok(numbered)
+}
+
+/// Rewrite `:name`, `$name` and `@name` placeholders into positional `?` placeholders,
+/// returning the rewritten query along with the parameters in the order they are referenced
+pub(crate) fn bind_named_params(
+    dialect: &dyn Dialect,
+    query: &str,
+    params: &[(&str, dbc::Value)],
+) -> Result<(String, Vec<dbc::Value>), dbc::Error> {
+    let tokens = tokenize(dialect, query)?;
+    let mut rewritten = String::with_capacity(query.len());
+    let mut values = Vec::new();
+    let mut last_end = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        let Some((name, len)) = named_placeholder(&tokens[i..]) else {
+            i += 1;
+            continue;
+        };
+        let value = params
+            .iter()
+            .find(|(param, _)| *param == name)
+            .map(|(_, value)| value.clone())
+            .ok_or_else(|| {
+                dbc::Error::Usage(format!("No value provided for named parameter {}", name))
+            })?;
+        values.push(value);
+        rewritten.push_str(&query[last_end..tokens[i].start]);
+        rewritten.push('?');
+        last_end = tokens[i + len - 1].end;
+        i += len;
+    }
+    rewritten.push_str(&query[last_end..]);
+    Ok((rewritten, values))
+}
+
+/// Match a named placeholder at the start of `tokens`, returning its name and token count.
+/// Depending on the dialect, `$name` and `@name` are tokenized as placeholders, words or an
+/// `@` sign followed by a word, while `:name` is always a colon followed by a word.
+fn named_placeholder(tokens: &[SpannedToken]) -> Option<(&str, usize)> {
+    let is_name = |name: &str| {
+        name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+    };
+
+    let (name, len) = match &tokens.first()?.token {
+        Token::Colon | Token::AtSign => {
+            let next = tokens.get(1)?;
+            if next.start != tokens[0].end {
+                return None;
+            }
+            (unquoted_word(next)?, 2)
+        }
+        Token::Placeholder(placeholder) => (placeholder.strip_prefix('$')?, 1),
+        Token::Word(_) => {
+            let word = unquoted_word(&tokens[0])?;
+            (word.strip_prefix(['$', '@'])?, 1)
+        }
+        _ => return None,
+    };
+    is_name(name).then_some((name, len))
+}
+
+fn unquoted_word(token: &SpannedToken) -> Option<&str> {
+    match &token.token {
+        Token::Word(word) if word.quote_style.is_none() => Some(word.value.as_str()),
+        _ => None,
+    }
+}
+
+/// Classify a statement by its leading keyword, or for `WITH` queries by the keyword following
+/// the common table expressions
+#[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+pub(crate) fn statement_kind(dialect: &dyn Dialect, query: &str) -> dbc::StatementKind {
+    let Ok(tokens) = Tokenizer::new(dialect, query).tokenize() else {
+        return dbc::StatementKind::Other;
+    };
+
+    let mut depth = 0_usize;
+    let mut is_with = false;
+    for token in tokens {
+        let word = match token {
+            Token::LParen => {
+                depth += 1;
+                continue;
+            }
+            Token::RParen => {
+                depth = depth.saturating_sub(1);
+                continue;
+            }
+            Token::Word(word) if word.quote_style.is_none() => word.value.to_ascii_uppercase(),
+            _ => continue,
+        };
+        let kind = match word.as_str() {
+            // The common table expressions are enclosed in parentheses
+            _ if is_with && depth > 0 => continue,
+            "WITH" => {
+                is_with = true;
+                continue;
+            }
+            "SELECT" | "VALUES" | "TABLE" => dbc::StatementKind::Query,
+            "SHOW" | "EXPLAIN" | "DESCRIBE" | "DESC" | "PRAGMA" if !is_with => {
+                dbc::StatementKind::Query
+            }
+            "INSERT" | "REPLACE" => dbc::StatementKind::Insert,
+            "UPDATE" => dbc::StatementKind::Update,
+            "DELETE" => dbc::StatementKind::Delete,
+            "CREATE" | "ALTER" | "DROP" | "TRUNCATE" | "RENAME" if !is_with => {
+                dbc::StatementKind::Ddl
+            }
+            "BEGIN" | "START" | "COMMIT" | "ROLLBACK" | "SAVEPOINT" | "RELEASE" | "END"
+                if !is_with =>
+            {
+                dbc::StatementKind::Transaction
+            }
+            // Names and AS keywords of common table expressions
+            _ if is_with => continue,
+            _ => dbc::StatementKind::Other,
+        };
+        return kind;
+    }
+    dbc::StatementKind::Other
+}
+
+/// Split a script into its `;` separated statements, skipping empty ones. Semicolons inside
+/// strings, comments and the `BEGIN ... END` bodies of triggers and routines do not end a
+/// statement.
+pub(crate) fn split_statements<'a>(
+    dialect: &dyn Dialect,
+    script: &'a str,
+) -> Result<Vec<&'a str>, dbc::Error> {
+    let tokens = tokenize(dialect, script)?;
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut is_empty = true;
+    let mut is_create = false;
+    let mut has_body = false;
+    let mut block_depth = 0_usize;
+    for (i, token) in tokens.iter().enumerate() {
+        match &token.token {
+            Token::SemiColon if block_depth == 0 => {
+                if !is_empty {
+                    statements.push(script[start..token.start].trim());
+                }
+                start = token.end;
+                is_empty = true;
+                is_create = false;
+                has_body = false;
+            }
+            Token::Whitespace(_) => {}
+            Token::Word(word) if word.quote_style.is_none() => {
+                let word = word.value.to_ascii_uppercase();
+                match word.as_str() {
+                    "CREATE" if is_empty => is_create = true,
+                    "TRIGGER" | "PROCEDURE" | "FUNCTION" if is_create => has_body = true,
+                    "BEGIN" | "CASE" if has_body => block_depth += 1,
+                    // END IF, END LOOP... close blocks whose opening keyword is not counted
+                    "END" if has_body && !ends_uncounted_block(&tokens[i + 1..]) => {
+                        block_depth = block_depth.saturating_sub(1)
+                    }
+                    _ => {}
+                }
+                is_empty = false;
+            }
+            _ => is_empty = false,
+        }
+    }
+    if !is_empty {
+        statements.push(script[start..].trim());
+    }
+    Ok(statements)
+}
+
+fn ends_uncounted_block(tokens: &[SpannedToken]) -> bool {
+    let next = tokens
+        .iter()
+        .find(|token| !matches!(token.token, Token::Whitespace(_)));
+    match next.map(|token| &token.token) {
+        Some(Token::Word(word)) => matches!(
+            word.value.to_ascii_uppercase().as_str(),
+            "IF" | "LOOP" | "WHILE" | "REPEAT"
+        ),
+        _ => false,
+    }
+}
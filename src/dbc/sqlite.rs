@@ -1,83 +1,477 @@
 use std::sync::Arc;
 
 use rusqlite;
+use sqlparser::dialect::{Dialect, SQLiteDialect};
 
 use crate::dbc;
+use crate::dbc::options::percent_encode;
 
 pub(crate) struct SQLiteConnection {
     connection: rusqlite::Connection,
 }
 
 impl SQLiteConnection {
-    pub(crate) fn get_connection(url: &str) -> Result<Box<dyn dbc::Connection>, dbc::Error> {
-        let connection;
-        if url == "sqlite://:memory:" {
-            connection = rusqlite::Connection::open_in_memory()?;
-        } else {
-            connection = rusqlite::Connection::open(url)?;
+    pub(crate) fn get_connection(
+        options: &dbc::SqliteOptions,
+    ) -> Result<Box<dyn dbc::Connection + Send>, dbc::Error> {
+        let (path, flags) = open_path_and_flags(options);
+        let connection = rusqlite::Connection::open_with_flags(path, flags)
+            .map_err(|err| dbc::Error::Connection(dbc::DatabaseError::from_source(err)))?;
+        if let Some(busy_timeout) = options.busy_timeout {
+            connection.busy_timeout(busy_timeout)?;
         }
-        Ok(Box::new(SQLiteConnection { connection }) as Box<dyn dbc::Connection>)
+        if let Some(journal_mode) = options.journal_mode {
+            // Setting the journal mode returns the resulting mode
+            connection.pragma_update_and_check(
+                None,
+                "journal_mode",
+                journal_mode.as_str(),
+                |_| Ok(()),
+            )?;
+        }
+        if let Some(foreign_keys) = options.foreign_keys {
+            connection.pragma_update(None, "foreign_keys", foreign_keys)?;
+        }
+        Ok(Box::new(SQLiteConnection { connection }) as Box<dyn dbc::Connection + Send>)
     }
 }
 
-impl dbc::Connection for SQLiteConnection {
-    fn execute(&mut self, query: &str) -> Result<dbc::QueryResult, dbc::Error> {
-        let mut statement = self.connection.prepare(query)?;
-        let columns = statement
-            .columns()
+fn open_path_and_flags(options: &dbc::SqliteOptions) -> (String, rusqlite::OpenFlags) {
+    use rusqlite::OpenFlags;
+
+    let mut flags = OpenFlags::default();
+    if let Some(mode) = options.mode {
+        flags -= OpenFlags::SQLITE_OPEN_READ_ONLY
+            | OpenFlags::SQLITE_OPEN_READ_WRITE
+            | OpenFlags::SQLITE_OPEN_CREATE;
+        flags |= match mode {
+            dbc::SqliteMode::ReadOnly => OpenFlags::SQLITE_OPEN_READ_ONLY,
+            dbc::SqliteMode::ReadWrite => OpenFlags::SQLITE_OPEN_READ_WRITE,
+            dbc::SqliteMode::ReadWriteCreate | dbc::SqliteMode::Memory => {
+                OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE
+            }
+        };
+    }
+    if options.mode == Some(dbc::SqliteMode::Memory) {
+        flags |= OpenFlags::SQLITE_OPEN_MEMORY;
+    }
+    match options.cache {
+        Some(dbc::SqliteCache::Shared) => flags |= OpenFlags::SQLITE_OPEN_SHARED_CACHE,
+        Some(dbc::SqliteCache::Private) => flags |= OpenFlags::SQLITE_OPEN_PRIVATE_CACHE,
+        None => {}
+    }
+
+    let mut path = options.path.clone();
+    if !options.uri_params.is_empty() {
+        let params = options
+            .uri_params
             .iter()
-            .map(|column| {
-                let sqlite_type = column.decl_type().unwrap();
-                dbc::Column {
-                    name: column.name().to_string(),
-                    column_type: sqlite_type.into(),
-                }
-            })
-            .collect::<Vec<dbc::Column>>();
-        let columns = Arc::from(columns);
-        let num_columns = statement.column_count();
-
-        if !query.starts_with("SELECT") {
-            let affected_rows = statement.execute([])?;
+            .map(|(name, value)| format!("{}={}", percent_encode(name), percent_encode(value)))
+            .collect::<Vec<String>>();
+        path = format!("{}?{}", path, params.join("&"));
+    }
+    // SQLite only shares the cache of named in-memory databases opened through a URI
+    if options.mode == Some(dbc::SqliteMode::Memory) && !path.starts_with("file:") {
+        path = format!("file:{}", percent_encode(&path));
+    }
+    (path, flags)
+}
+
+impl dbc::Connection for SQLiteConnection {
+    fn prepare(&mut self, query: &str) -> Result<Box<dyn dbc::Statement + '_>, dbc::Error> {
+        prepare(&self.connection, query)
+    }
+
+    fn query_iter(
+        &mut self,
+        query: &str,
+        params: &[dbc::Value],
+    ) -> Result<dbc::RowCursor<'_>, dbc::Error> {
+        query_iter(&self.connection, query, params)
+    }
+
+    fn transaction(&mut self) -> Result<Box<dyn dbc::TransactionConnection + '_>, dbc::Error> {
+        Ok(Box::new(SQLiteTransaction {
+            transaction: self.connection.transaction()?,
+        }))
+    }
+
+    fn dialect(&self) -> &'static dyn Dialect {
+        &SQLiteDialect {}
+    }
+
+    fn ping(&mut self) -> Result<(), dbc::Error> {
+        Ok(self.connection.query_row("SELECT 1", [], |_| Ok(()))?)
+    }
+}
+
+pub(crate) struct SQLiteTransaction<'conn> {
+    transaction: rusqlite::Transaction<'conn>,
+}
+
+impl dbc::Connection for SQLiteTransaction<'_> {
+    fn prepare(&mut self, query: &str) -> Result<Box<dyn dbc::Statement + '_>, dbc::Error> {
+        prepare(&self.transaction, query)
+    }
+
+    fn query_iter(
+        &mut self,
+        query: &str,
+        params: &[dbc::Value],
+    ) -> Result<dbc::RowCursor<'_>, dbc::Error> {
+        query_iter(&self.transaction, query, params)
+    }
+
+    fn transaction(&mut self) -> Result<Box<dyn dbc::TransactionConnection + '_>, dbc::Error> {
+        Err(dbc::Error::Usage("Transactions cannot be nested".into()))
+    }
+
+    fn dialect(&self) -> &'static dyn Dialect {
+        &SQLiteDialect {}
+    }
+}
+
+impl dbc::TransactionConnection for SQLiteTransaction<'_> {
+    fn commit(self: Box<Self>) -> Result<(), dbc::Error> {
+        Ok(self.transaction.commit()?)
+    }
+
+    fn rollback(self: Box<Self>) -> Result<(), dbc::Error> {
+        Ok(self.transaction.rollback()?)
+    }
+
+    fn savepoint(
+        &mut self,
+        name: &str,
+    ) -> Result<Box<dyn dbc::TransactionConnection + '_>, dbc::Error> {
+        Ok(Box::new(SQLiteSavepoint {
+            savepoint: self.transaction.savepoint_with_name(name)?,
+        }))
+    }
+}
+
+pub(crate) struct SQLiteSavepoint<'conn> {
+    savepoint: rusqlite::Savepoint<'conn>,
+}
+
+impl dbc::Connection for SQLiteSavepoint<'_> {
+    fn prepare(&mut self, query: &str) -> Result<Box<dyn dbc::Statement + '_>, dbc::Error> {
+        prepare(&self.savepoint, query)
+    }
+
+    fn query_iter(
+        &mut self,
+        query: &str,
+        params: &[dbc::Value],
+    ) -> Result<dbc::RowCursor<'_>, dbc::Error> {
+        query_iter(&self.savepoint, query, params)
+    }
+
+    fn transaction(&mut self) -> Result<Box<dyn dbc::TransactionConnection + '_>, dbc::Error> {
+        Err(dbc::Error::Usage("Transactions cannot be nested".into()))
+    }
+
+    fn dialect(&self) -> &'static dyn Dialect {
+        &SQLiteDialect {}
+    }
+}
+
+impl dbc::TransactionConnection for SQLiteSavepoint<'_> {
+    fn commit(self: Box<Self>) -> Result<(), dbc::Error> {
+        Ok(self.savepoint.commit()?)
+    }
+
+    fn rollback(self: Box<Self>) -> Result<(), dbc::Error> {
+        let mut savepoint = self.savepoint;
+        savepoint.rollback()?;
+        // Rolling back keeps the savepoint open, release it like it would be on drop
+        Ok(savepoint.commit()?)
+    }
+
+    fn savepoint(
+        &mut self,
+        name: &str,
+    ) -> Result<Box<dyn dbc::TransactionConnection + '_>, dbc::Error> {
+        Ok(Box::new(SQLiteSavepoint {
+            savepoint: self.savepoint.savepoint_with_name(name)?,
+        }))
+    }
+}
+
+fn prepare<'conn>(
+    connection: &'conn rusqlite::Connection,
+    query: &str,
+) -> Result<Box<dyn dbc::Statement + 'conn>, dbc::Error> {
+    let statement = connection.prepare(query)?;
+    Ok(Box::new(SQLiteStatement {
+        columns: columns(&statement),
+        statement,
+        kind: dbc::sql::statement_kind(&SQLiteDialect {}, query),
+    }))
+}
+
+fn query_iter<'conn>(
+    connection: &'conn rusqlite::Connection,
+    query: &str,
+    params: &[dbc::Value],
+) -> Result<dbc::RowCursor<'conn>, dbc::Error> {
+    let statement = connection.prepare(query)?;
+    let columns = columns(&statement);
+    let rows = SQLiteOwnedRows::query(statement, params, Arc::clone(&columns))?;
+    Ok(dbc::RowCursor::new(columns, rows))
+}
+
+fn columns(statement: &rusqlite::Statement) -> Arc<[dbc::Column]> {
+    statement
+        .columns()
+        .iter()
+        .map(|column| dbc::Column {
+            name: column.name().to_string(),
+            // Columns which are not read from a table, like expressions or PRAGMA results, have
+            // no declared type until SQLiteRows reads their values
+            column_type: column
+                .decl_type()
+                .map_or(dbc::ColumnType::UNKNOWN, dbc::ColumnType::from),
+        })
+        .collect()
+}
+
+pub(crate) struct SQLiteStatement<'conn> {
+    statement: rusqlite::Statement<'conn>,
+    columns: Arc<[dbc::Column]>,
+    kind: dbc::StatementKind,
+}
+
+impl dbc::Statement for SQLiteStatement<'_> {
+    fn execute(&mut self, params: &[dbc::Value]) -> Result<dbc::QueryResult, dbc::Error> {
+        // Any statement with result columns returns rows, including PRAGMA or INSERT ... RETURNING
+        if self.statement.column_count() == 0 {
+            let affected_rows = self.statement.execute(rusqlite::params_from_iter(params))?;
             return Ok(dbc::QueryResult {
                 rows: Vec::new(),
+                columns: Arc::clone(&self.columns),
                 affected_row_count: affected_rows,
+                kind: self.kind,
             });
         }
 
-        let mut rows: Vec<dbc::Row> = Vec::new();
-        let mut result = statement.query([])?;
-        while let Some(row) = result.next()? {
-            let mut values: Vec<dbc::Value> = Vec::new();
-            for i in 0..num_columns {
-                let value = row.get_ref(i).unwrap().into();
-                values.push(value);
+        let kind = self.kind;
+        Ok(dbc::QueryResult::try_from(self.query(params)?)?.with_kind(kind))
+    }
+
+    fn query(&mut self, params: &[dbc::Value]) -> Result<dbc::RowCursor<'_>, dbc::Error> {
+        let rows = self.statement.query(rusqlite::params_from_iter(params))?;
+        Ok(dbc::RowCursor::new(
+            Arc::clone(&self.columns),
+            SQLiteRows::new(rows, Arc::clone(&self.columns)),
+        ))
+    }
+
+    fn parameter_count(&self) -> usize {
+        self.statement.parameter_count()
+    }
+
+    fn columns(&self) -> &[dbc::Column] {
+        &self.columns
+    }
+}
+
+/// Rows read one at a time from a statement
+struct SQLiteRows<'stmt> {
+    rows: rusqlite::Rows<'stmt>,
+    columns: Arc<[dbc::Column]>,
+    // Whether some columns have no declared type yet
+    is_untyped: bool,
+}
+
+impl<'stmt> SQLiteRows<'stmt> {
+    fn new(rows: rusqlite::Rows<'stmt>, columns: Arc<[dbc::Column]>) -> Self {
+        SQLiteRows {
+            rows,
+            is_untyped: has_untyped_columns(&columns),
+            columns,
+        }
+    }
+
+    /// Columns without a declared type take the type of the first non-NULL value read from them
+    fn resolve_column_types(&mut self, data_types: &[rusqlite::types::Type]) {
+        use rusqlite::types::Type;
+
+        let mut columns = self.columns.to_vec();
+        for (column, data_type) in columns.iter_mut().zip(data_types) {
+            if matches!(column.column_type, dbc::ColumnType::UNKNOWN) {
+                column.column_type = match data_type {
+                    Type::Null => continue,
+                    Type::Integer => dbc::ColumnType::INT,
+                    Type::Real => dbc::ColumnType::DOUBLE,
+                    Type::Text => dbc::ColumnType::VARCHAR,
+                    Type::Blob => dbc::ColumnType::BLOB,
+                };
             }
+        }
+        self.is_untyped = has_untyped_columns(&columns);
+        self.columns = columns.into();
+    }
+}
 
-            rows.push(dbc::Row {
-                values,
-                columns: Arc::clone(&columns),
-            });
+fn has_untyped_columns(columns: &[dbc::Column]) -> bool {
+    columns
+        .iter()
+        .any(|column| matches!(column.column_type, dbc::ColumnType::UNKNOWN))
+}
+
+impl Iterator for SQLiteRows<'_> {
+    type Item = Result<dbc::Row, dbc::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let row = match self.rows.next() {
+            Ok(Some(row)) => row,
+            Ok(None) => return None,
+            Err(err) => return Some(Err(err.into())),
+        };
+        let mut values = Vec::with_capacity(self.columns.len());
+        let mut data_types = Vec::new();
+        for i in 0..self.columns.len() {
+            let value = match row.get_ref(i) {
+                Ok(value) => value,
+                Err(err) => return Some(Err(err.into())),
+            };
+            if self.is_untyped {
+                data_types.push(value.data_type());
+            }
+            values.push(value.into());
         }
-        Ok(dbc::QueryResult {
-            rows,
-            affected_row_count: 0_usize,
+        if self.is_untyped {
+            self.resolve_column_types(&data_types);
+        }
+        Some(Ok(dbc::Row {
+            values,
+            columns: Arc::clone(&self.columns),
+        }))
+    }
+}
+
+/// Rows read from a statement owned by the iterator itself, as rusqlite's `Rows` borrow the
+/// statement they read from. The rows are dropped before the statement.
+#[ouroboros::self_referencing]
+struct SQLiteOwnedRows<'conn> {
+    statement: rusqlite::Statement<'conn>,
+    #[borrows(mut statement)]
+    #[covariant]
+    rows: SQLiteRows<'this>,
+}
+
+impl<'conn> SQLiteOwnedRows<'conn> {
+    fn query(
+        statement: rusqlite::Statement<'conn>,
+        params: &[dbc::Value],
+        columns: Arc<[dbc::Column]>,
+    ) -> Result<Self, dbc::Error> {
+        SQLiteOwnedRows::try_new(statement, |statement| {
+            let rows = statement.query(rusqlite::params_from_iter(params))?;
+            Ok(SQLiteRows::new(rows, columns))
         })
     }
 }
 
+impl Iterator for SQLiteOwnedRows<'_> {
+    type Item = Result<dbc::Row, dbc::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.with_rows_mut(|rows| rows.next())
+    }
+}
+
+impl From<rusqlite::Error> for dbc::Error {
+    fn from(err: rusqlite::Error) -> Self {
+        use rusqlite::{ffi, ErrorCode};
+
+        let (code, extended_code) = match &err {
+            rusqlite::Error::SqliteFailure(failure, _) => (failure.code, failure.extended_code),
+            // Only reported by the newer SQLite versions bundled with rusqlite
+            #[cfg(feature = "sqlite-bundled")]
+            rusqlite::Error::SqlInputError { error, .. } => (error.code, error.extended_code),
+            rusqlite::Error::FromSqlConversionFailure(..)
+            | rusqlite::Error::IntegralValueOutOfRange(..)
+            | rusqlite::Error::InvalidColumnType(..)
+            | rusqlite::Error::ToSqlConversionFailure(_)
+            | rusqlite::Error::Utf8Error(_) => {
+                return dbc::Error::TypeConversion(dbc::DatabaseError::from_source(err))
+            }
+            rusqlite::Error::InvalidParameterCount(..)
+            | rusqlite::Error::InvalidParameterName(_) => {
+                return dbc::Error::Usage(err.to_string())
+            }
+            _ => return dbc::Error::Database(dbc::DatabaseError::from_source(err)),
+        };
+        let error = dbc::DatabaseError::from_source(err).with_code(extended_code);
+        match code {
+            ErrorCode::ConstraintViolation => {
+                let kind = match extended_code {
+                    ffi::SQLITE_CONSTRAINT_UNIQUE | ffi::SQLITE_CONSTRAINT_PRIMARYKEY => {
+                        dbc::ConstraintKind::Unique
+                    }
+                    ffi::SQLITE_CONSTRAINT_FOREIGNKEY => dbc::ConstraintKind::ForeignKey,
+                    ffi::SQLITE_CONSTRAINT_NOTNULL => dbc::ConstraintKind::NotNull,
+                    ffi::SQLITE_CONSTRAINT_CHECK => dbc::ConstraintKind::Check,
+                    _ => dbc::ConstraintKind::Other,
+                };
+                dbc::Error::ConstraintViolation { kind, error }
+            }
+            // The busy timeout ran out waiting for another connection to release its lock
+            ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked => dbc::Error::Timeout(error),
+            ErrorCode::CannotOpen | ErrorCode::NotADatabase => dbc::Error::Connection(error),
+            // SQLite has no dedicated result code for syntax errors
+            ErrorCode::Unknown
+                if error.message().contains("syntax error")
+                    || error.message().contains("incomplete input") =>
+            {
+                dbc::Error::Syntax(error)
+            }
+            _ => dbc::Error::Database(error),
+        }
+    }
+}
+
 impl From<rusqlite::types::ValueRef<'_>> for dbc::Value {
     fn from(value: rusqlite::types::ValueRef) -> Self {
         match value {
             rusqlite::types::ValueRef::Null => dbc::Value::NULL,
             rusqlite::types::ValueRef::Integer(i) => dbc::Value::Int(i),
             rusqlite::types::ValueRef::Real(f) => dbc::Value::Double(f),
-            rusqlite::types::ValueRef::Text(s) => dbc::Value::Bytes(s.to_vec()),
+            rusqlite::types::ValueRef::Text(s) => dbc::Value::from_text(s.to_vec()),
             rusqlite::types::ValueRef::Blob(b) => dbc::Value::Bytes(b.to_vec()),
         }
     }
 }
 
+impl rusqlite::ToSql for dbc::Value {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        use rusqlite::types::{ToSqlOutput, ValueRef};
+
+        let value = match self {
+            dbc::Value::NULL => ValueRef::Null,
+            dbc::Value::Bytes(bytes) => ValueRef::Blob(bytes),
+            dbc::Value::String(string) => ValueRef::Text(string.as_bytes()),
+            dbc::Value::Bool(bool) => ValueRef::Integer(*bool as i64),
+            dbc::Value::Int(int) => ValueRef::Integer(*int),
+            dbc::Value::UInt(uint) => ValueRef::Integer(
+                i64::try_from(*uint)
+                    .map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))?,
+            ),
+            dbc::Value::Float(float) => ValueRef::Real(*float as f64),
+            dbc::Value::Double(double) => ValueRef::Real(*double),
+            // SQLite has no date and time types, they are stored as text
+            dbc::Value::Date(..) | dbc::Value::Time(..) => {
+                return Ok(ToSqlOutput::from(
+                    self.to_sql_text().expect("dates and times have a text"),
+                ))
+            }
+        };
+        Ok(ToSqlOutput::Borrowed(value))
+    }
+}
+
 impl From<&str> for dbc::ColumnType {
     fn from(sqlite_type: &str) -> Self {
         match sqlite_type {
@@ -0,0 +1,221 @@
+use std::sync::Arc;
+
+use rdbc2::dbc;
+
+type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+fn _columns(names: &[&str]) -> Arc<[dbc::Column]> {
+    names
+        .iter()
+        .map(|name| dbc::Column {
+            name: name.to_string(),
+            column_type: dbc::ColumnType::UNKNOWN,
+        })
+        .collect()
+}
+
+#[test]
+fn test_binary_round_trip_values() -> Result<(), Error> {
+    let values = vec![
+        dbc::Value::NULL,
+        dbc::Value::Bytes(vec![0xff, 0x00, 0x80]),
+        dbc::Value::Bytes(vec![]),
+        dbc::Value::String("héllo \"wörld\"".to_owned()),
+        dbc::Value::String(String::new()),
+        dbc::Value::Bool(true),
+        dbc::Value::Bool(false),
+        dbc::Value::Int(i64::MIN),
+        dbc::Value::Int(-1),
+        dbc::Value::Int(0),
+        dbc::Value::Int(i64::MAX),
+        dbc::Value::UInt(u64::MAX),
+        dbc::Value::Float(f32::NEG_INFINITY),
+        dbc::Value::Float(1.5e-40),
+        dbc::Value::Double(f64::MAX),
+        dbc::Value::Double(-0.0),
+        dbc::Value::Date(9999, 12, 31, 23, 59, 59, 999999),
+        dbc::Value::Date(0, 1, 1, 0, 0, 0, 0),
+        dbc::Value::Time(true, u32::MAX, 23, 59, 59, 999999),
+        dbc::Value::Time(false, 0, 0, 0, 0, 0),
+    ];
+    let names = (0..values.len())
+        .map(|index| format!("column {}", index))
+        .collect::<Vec<_>>();
+    let columns = _columns(&names.iter().map(String::as_str).collect::<Vec<_>>());
+    let result = dbc::QueryResult {
+        rows: vec![dbc::Row::new(values.clone(), Arc::clone(&columns))],
+        columns,
+        affected_row_count: 1,
+        kind: dbc::StatementKind::Insert,
+    };
+
+    let decoded = dbc::QueryResult::from_bytes(&result.to_bytes()?)?;
+    assert_eq!(decoded.affected_row_count, 1);
+    assert_eq!(decoded.kind, dbc::StatementKind::Insert);
+    for (index, value) in values.iter().enumerate() {
+        assert_eq!(decoded.rows[0].get_value(index), Some(value));
+        assert_eq!(
+            decoded.rows[0].get_column(index).unwrap().name,
+            names[index]
+        );
+    }
+    // -0.0 == 0.0, the sign is kept as well
+    assert!(
+        matches!(decoded.rows[0].get_value(15), Some(dbc::Value::Double(double)) if double.is_sign_negative())
+    );
+
+    // NaN is not equal to itself, its bits are kept
+    let columns = _columns(&["nan"]);
+    let nan = f64::from_bits(0x7ff8_0000_0000_0001);
+    let result = dbc::QueryResult {
+        rows: vec![dbc::Row::new(
+            vec![dbc::Value::Double(nan)],
+            Arc::clone(&columns),
+        )],
+        columns,
+        affected_row_count: 0,
+        kind: dbc::StatementKind::Query,
+    };
+    let decoded = dbc::QueryResult::from_bytes(&result.to_bytes()?)?;
+    assert!(
+        matches!(decoded.rows[0].get_value(0), Some(dbc::Value::Double(double)) if double.to_bits() == nan.to_bits())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_binary_round_trip_empty_result() -> Result<(), Error> {
+    let result = dbc::QueryResult {
+        rows: Vec::new(),
+        columns: _columns(&["id", "name"]),
+        affected_row_count: 0,
+        kind: dbc::StatementKind::Query,
+    };
+    let decoded = dbc::QueryResult::from_bytes(&result.to_bytes()?)?;
+    assert!(decoded.rows.is_empty());
+    let names = decoded
+        .columns
+        .iter()
+        .map(|column| column.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["id", "name"]);
+    Ok(())
+}
+
+#[test]
+fn test_binary_round_trip_column_types() -> Result<(), Error> {
+    let column_types = [
+        dbc::ColumnType::NULL,
+        dbc::ColumnType::DECIMAL,
+        dbc::ColumnType::INT,
+        dbc::ColumnType::FLOAT,
+        dbc::ColumnType::BIT,
+        dbc::ColumnType::DOUBLE,
+        dbc::ColumnType::STRING,
+        dbc::ColumnType::VARCHAR,
+        dbc::ColumnType::TIMESTAMP,
+        dbc::ColumnType::DATE,
+        dbc::ColumnType::TIME,
+        dbc::ColumnType::YEAR,
+        dbc::ColumnType::DATETIME,
+        dbc::ColumnType::JSON,
+        dbc::ColumnType::ENUM,
+        dbc::ColumnType::SET,
+        dbc::ColumnType::BLOB,
+        dbc::ColumnType::GEOMETRY,
+        dbc::ColumnType::UNKNOWN,
+    ];
+    let columns = column_types
+        .iter()
+        .map(|column_type| dbc::Column {
+            name: format!("{:?}", column_type),
+            column_type: column_type.clone(),
+        })
+        .collect::<Vec<_>>();
+    let bytes = dbc::Encoder::new(Vec::new(), &columns)?.finish(0, dbc::StatementKind::Query)?;
+    let decoder = dbc::Decoder::new(&bytes[..])?;
+    let decoded = decoder
+        .columns()
+        .iter()
+        .map(|column| format!("{:?}", column.column_type))
+        .collect::<Vec<_>>();
+    let expected = columns
+        .iter()
+        .map(|column| column.name.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(decoded, expected);
+    Ok(())
+}
+
+#[test]
+fn test_binary_streaming() -> Result<(), Error> {
+    let columns = _columns(&["id", "label"]);
+    let mut encoder = dbc::Encoder::new(Vec::new(), &columns)?;
+    for id in 0..100 {
+        let values = vec![
+            dbc::Value::Int(id),
+            dbc::Value::String(format!("name {}", id)),
+        ];
+        encoder.encode_row(&dbc::Row::new(values, columns.clone()))?;
+    }
+    let bytes = encoder.finish(0, dbc::StatementKind::Query)?;
+    // The column names are written once, not with every row
+    let label_count = bytes.windows(5).filter(|window| window == b"label").count();
+    assert_eq!(label_count, 1);
+
+    let mut decoder = dbc::Decoder::new(&bytes[..])?;
+    assert_eq!(decoder.columns().len(), 2);
+    let first = decoder.decode_row()?.unwrap();
+    assert_eq!(
+        first.get_value_by_name("label"),
+        Some(&dbc::Value::String("name 0".to_owned()))
+    );
+    assert_eq!(decoder.kind(), None);
+    let rest = decoder.by_ref().collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(rest.len(), 99);
+    assert_eq!(rest[98].get_value(0), Some(&dbc::Value::Int(99)));
+    assert_eq!(decoder.kind(), Some(dbc::StatementKind::Query));
+    assert_eq!(decoder.affected_row_count(), Some(0));
+    assert!(decoder.decode_row()?.is_none());
+    Ok(())
+}
+
+#[test]
+fn test_binary_invalid_input() -> Result<(), Error> {
+    let columns = _columns(&["id"]);
+    let mut encoder = dbc::Encoder::new(Vec::new(), &columns)?;
+    let row = dbc::Row::new(
+        vec![dbc::Value::Int(1), dbc::Value::Int(2)],
+        columns.clone(),
+    );
+    assert!(matches!(
+        encoder.encode_row(&row),
+        Err(dbc::Error::Usage(_))
+    ));
+    encoder.encode_row(&dbc::Row::new(vec![dbc::Value::Int(1)], columns))?;
+    let bytes = encoder.finish(1, dbc::StatementKind::Query)?;
+
+    let mut invalid = bytes.clone();
+    invalid[0] = b'X';
+    let err = dbc::QueryResult::from_bytes(&invalid).unwrap_err();
+    assert!(
+        err.to_string().contains("not an encoded query result"),
+        "{}",
+        err
+    );
+
+    // The value tag follows the header and the row marker
+    let mut invalid = bytes.clone();
+    let tag = bytes.len() - 5;
+    assert_eq!(invalid[tag], 4);
+    invalid[tag] = 42;
+    let err = dbc::QueryResult::from_bytes(&invalid).unwrap_err();
+    assert!(err.to_string().contains("unknown value tag 42"), "{}", err);
+
+    let mut invalid = bytes.clone();
+    invalid.push(0);
+    let err = dbc::QueryResult::from_bytes(&invalid).unwrap_err();
+    assert!(err.to_string().contains("trailing bytes"), "{}", err);
+    Ok(())
+}
@@ -1,13 +1,17 @@
+#![allow(dead_code)]
+
 use rdbc2::dbc;
 
-fn _cleanup_database(mut database: dbc::Database) -> Result<(), dbc::Error> {
+type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+fn _cleanup_database(mut database: dbc::Database) -> Result<(), Error> {
     let query = "DROP TABLE IF EXISTS test_table";
     database.execute_query(query)?;
 
     Ok(())
 }
 
-pub(crate) async fn test_simple_query(mut database: dbc::Database) -> Result<(), dbc::Error> {
+pub(crate) async fn test_simple_query(mut database: dbc::Database) -> Result<(), Error> {
     // Create a test table with two rows
     let insert_query = "INSERT INTO test_table (name) VALUES ('test1'), ('test2')";
     database.execute_query(insert_query)?;
@@ -22,28 +26,28 @@ pub(crate) async fn test_simple_query(mut database: dbc::Database) -> Result<(),
     let second_row = &result.rows[1];
     assert_eq!(
         first_row.get_value_by_name("name"),
-        Some(&dbc::Value::Bytes("test1".to_owned().into_bytes()))
+        Some(&dbc::Value::String("test1".to_owned()))
     );
     assert_eq!(
         second_row.get_value_by_name("name"),
-        Some(&dbc::Value::Bytes("test2".to_owned().into_bytes()))
+        Some(&dbc::Value::String("test2".to_owned()))
     );
 
     // Update the name of the first row to "updated"
     let update_query = "UPDATE test_table SET name = ? WHERE id = ?";
-    let result = database.execute_query_with_params(update_query, &["updated", "1"])?;
+    let result = database.execute_query_with_params(update_query, &["updated".into(), 1.into()])?;
     assert_eq!(result.affected_row_count, 1);
 
     // Select the first row from test_table where the name is "updated"
     let select_query = "SELECT * FROM test_table WHERE name = ?";
-    let result = database.execute_query_with_params(select_query, &["updated"])?;
+    let result = database.execute_query_with_params(select_query, &["updated".into()])?;
     assert_eq!(result.rows.len(), 1);
 
     // Verify the data returned by the query
     let first_row = &result.rows[0];
     assert_eq!(
         first_row.get_value_by_name("name"),
-        Some(&dbc::Value::Bytes("updated".to_owned().into_bytes()))
+        Some(&dbc::Value::String("updated".to_owned()))
     );
 
     _cleanup_database(database)?;
@@ -51,12 +55,12 @@ pub(crate) async fn test_simple_query(mut database: dbc::Database) -> Result<(),
     Ok(())
 }
 
-pub(crate) async fn test_query_with_params(mut database: dbc::Database) -> Result<(), dbc::Error> {
+pub(crate) async fn test_query_with_params(mut database: dbc::Database) -> Result<(), Error> {
     // Insert two rows into test_table
     let insert_query = "INSERT INTO test_table (name) VALUES (?)";
-    let result = database.execute_query_with_params(insert_query, &["test1"])?;
+    let result = database.execute_query_with_params(insert_query, &["test1".into()])?;
     assert_eq!(result.affected_row_count, 1);
-    let result = database.execute_query_with_params(insert_query, &["test2"])?;
+    let result = database.execute_query_with_params(insert_query, &["test2".into()])?;
     assert_eq!(result.affected_row_count, 1);
 
     // Select all rows from test_table
@@ -69,28 +73,28 @@ pub(crate) async fn test_query_with_params(mut database: dbc::Database) -> Resul
     let second_row = &result.rows[1];
     assert_eq!(
         first_row.get_value_by_name("name"),
-        Some(&dbc::Value::Bytes("test1".to_owned().into_bytes()))
+        Some(&dbc::Value::String("test1".to_owned()))
     );
     assert_eq!(
         second_row.get_value_by_name("name"),
-        Some(&dbc::Value::Bytes("test2".to_owned().into_bytes()))
+        Some(&dbc::Value::String("test2".to_owned()))
     );
 
     // Update the name of the first row to "updated"
     let update_query = "UPDATE test_table SET name = ? WHERE id = ?";
-    let result = database.execute_query_with_params(update_query, &["updated", "1"])?;
+    let result = database.execute_query_with_params(update_query, &["updated".into(), 1.into()])?;
     assert_eq!(result.affected_row_count, 1);
 
     // Select the first row from test_table where the name is "updated"
     let select_query = "SELECT * FROM test_table WHERE name = ?";
-    let result = database.execute_query_with_params(select_query, &["updated"])?;
+    let result = database.execute_query_with_params(select_query, &["updated".into()])?;
     assert_eq!(result.rows.len(), 1);
 
     // Verify the data returned by the query
     let first_row = &result.rows[0];
     assert_eq!(
         first_row.get_value_by_name("name"),
-        Some(&dbc::Value::Bytes("updated".to_owned().into_bytes()))
+        Some(&dbc::Value::String("updated".to_owned()))
     );
 
     _cleanup_database(database)?;
@@ -100,31 +104,824 @@ pub(crate) async fn test_query_with_params(mut database: dbc::Database) -> Resul
 
 pub(crate) async fn test_query_with_params_and_serialize(
     mut database: dbc::Database,
-) -> Result<(), dbc::Error> {
+) -> Result<(), Error> {
     // Insert two rows into test_table
     let insert_query = "INSERT INTO test_table (name) VALUES (?)";
-    let result = database.execute_query_with_params(insert_query, &["test1"])?;
+    let result = database.execute_query_with_params(insert_query, &["test1".into()])?;
     assert_eq!(result.affected_row_count, 1);
-    let result = database.execute_query_with_params(insert_query, &["test2"])?;
+    let result = database.execute_query_with_params(insert_query, &["test2".into()])?;
     assert_eq!(result.affected_row_count, 1);
 
     // Update the name of the first row to "updated"
     let update_query = "UPDATE test_table SET name = ? WHERE id = ?";
-    let result = database.execute_query_with_params(update_query, &["updated", "1"])?;
+    let result = database.execute_query_with_params(update_query, &["updated".into(), 1.into()])?;
     assert_eq!(result.affected_row_count, 1);
 
-    // Select the first row from test_table where the name is "updated"
+    // Select the first row from test_table where the name is "updated" and serialize it
     let select_query = "SELECT * FROM test_table WHERE id = ?";
-    let result = database.execute_query_with_params(select_query, &["1"])?;
-
-    // Serialize the result
-    let result = serde_json::to_string(&result)?;
+    let result = database.execute_query_and_serialize_with_params(select_query, &[1.into()])?;
 
     // Verify the data returned by the query
-    let expected_result = r#"{"rows":[{"values":[{"Int":1},{"Bytes":"dXBkYXRlZA"}],"columns":[{"name":"id","column_type":"INT"},{"name":"name","column_type":"VARCHAR"}]}],"affected_row_count":0}"#;
+    let expected_result = r#"{"rows":[{"values":[{"Int":1},{"String":"updated"}],"columns":[{"name":"id","column_type":"INT"},{"name":"name","column_type":"VARCHAR"}]}],"columns":[{"name":"id","column_type":"INT"},{"name":"name","column_type":"VARCHAR"}],"affected_row_count":0,"kind":"Query"}"#;
     assert_eq!(result, expected_result);
 
     _cleanup_database(database)?;
 
     Ok(())
 }
+
+pub(crate) async fn test_query_with_params_escaping(
+    mut database: dbc::Database,
+) -> Result<(), Error> {
+    // Insert rows whose names contain quotes and placeholders
+    let insert_query = "INSERT INTO test_table (name) VALUES (?)";
+    for name in ["it's", "'quoted'", "?"] {
+        let result = database.execute_query_with_params(insert_query, &[name.into()])?;
+        assert_eq!(result.affected_row_count, 1);
+    }
+
+    // A question mark inside a string literal is not a placeholder
+    let select_query = "SELECT * FROM test_table WHERE name = '?' OR name = ?";
+    let result = database.execute_query_with_params(select_query, &["it's".into()])?;
+    assert_eq!(result.rows.len(), 2);
+
+    // Parameters are never interpreted as SQL
+    let select_query = "SELECT * FROM test_table WHERE name = ?";
+    let result = database.execute_query_with_params(select_query, &["'quoted'".into()])?;
+    assert_eq!(result.rows.len(), 1);
+    assert_eq!(
+        result.rows[0].get_value_by_name("name"),
+        Some(&dbc::Value::String("'quoted'".to_owned()))
+    );
+    let result = database.execute_query_with_params(select_query, &["' OR '1' = '1".into()])?;
+    assert_eq!(result.rows.len(), 0);
+
+    _cleanup_database(database)?;
+
+    Ok(())
+}
+
+pub(crate) async fn test_query_with_typed_params(mut database: dbc::Database) -> Result<(), Error> {
+    // Insert a row into typed_table with a value of each kind, including NULL
+    let insert_query = "INSERT INTO typed_table (id, amount, data, note) VALUES (?, ?, ?, ?)";
+    let params = [
+        dbc::Value::Int(7),
+        2.5.into(),
+        vec![0_u8, 1, 2].into(),
+        Option::<String>::None.into(),
+    ];
+    let result = database.execute_query_with_params(insert_query, &params)?;
+    assert_eq!(result.affected_row_count, 1);
+
+    // Select the row back by its integer id
+    let select_query = "SELECT * FROM typed_table WHERE id = ?";
+    let result = database.execute_query_with_params(select_query, &[7.into()])?;
+    assert_eq!(result.rows.len(), 1);
+
+    // Verify the values kept their types
+    let row = &result.rows[0];
+    assert_eq!(row.get_value_by_name("id"), Some(&dbc::Value::Int(7)));
+    assert_eq!(
+        row.get_value_by_name("amount"),
+        Some(&dbc::Value::Double(2.5))
+    );
+    assert_eq!(
+        row.get_value_by_name("data"),
+        Some(&dbc::Value::Bytes(vec![0, 1, 2]))
+    );
+    assert_eq!(row.get_value_by_name("note"), Some(&dbc::Value::NULL));
+
+    // NULL parameters never compare equal
+    let select_query = "SELECT * FROM typed_table WHERE note = ?";
+    let result = database.execute_query_with_params(select_query, &[dbc::Value::NULL])?;
+    assert_eq!(result.rows.len(), 0);
+
+    let query = "DROP TABLE IF EXISTS typed_table";
+    database.execute_query(query)?;
+
+    Ok(())
+}
+
+pub(crate) async fn test_query_with_named_params(mut database: dbc::Database) -> Result<(), Error> {
+    // Insert two rows into test_table using each placeholder style
+    let insert_query = "INSERT INTO test_table (name) VALUES (:name)";
+    let result =
+        database.execute_query_with_named_params(insert_query, &[("name", "test1".into())])?;
+    assert_eq!(result.affected_row_count, 1);
+    let insert_query = "INSERT INTO test_table (name) VALUES (@name)";
+    let result =
+        database.execute_query_with_named_params(insert_query, &[("name", "test2".into())])?;
+    assert_eq!(result.affected_row_count, 1);
+
+    // Update the name of the first row to "updated"
+    let update_query = "UPDATE test_table SET name = $name WHERE id = $id";
+    let params = [("id", 1.into()), ("name", "updated".into())];
+    let result = database.execute_query_with_named_params(update_query, &params)?;
+    assert_eq!(result.affected_row_count, 1);
+
+    // The same parameter can be referenced several times, but not from inside a string literal
+    let select_query =
+        "SELECT * FROM test_table WHERE name = :name OR name = ':name' OR id + :id = 3 OR name = :name";
+    let params = [("name", "updated".into()), ("id", 1.into())];
+    let result = database.execute_query_with_named_params(select_query, &params)?;
+    assert_eq!(result.rows.len(), 2);
+
+    // Every referenced parameter must be provided
+    let select_query = "SELECT * FROM test_table WHERE name = :missing";
+    assert!(matches!(
+        database.execute_query_with_named_params(select_query, &[("name", "updated".into())]),
+        Err(dbc::Error::Usage(_))
+    ));
+
+    _cleanup_database(database)?;
+
+    Ok(())
+}
+
+pub(crate) async fn test_prepared_statement(mut database: dbc::Database) -> Result<(), Error> {
+    // Insert three rows into test_table reusing a single prepared statement
+    let mut statement = database.prepare("INSERT INTO test_table (name) VALUES (?)")?;
+    assert_eq!(statement.parameter_count(), 1);
+    assert!(statement.columns().is_empty());
+    for name in ["test1", "test2", "test3"] {
+        let result = statement.execute(&[name.into()])?;
+        assert_eq!(result.affected_row_count, 1);
+    }
+    drop(statement);
+
+    // The result columns are known before the statement is executed
+    let mut statement = database.prepare("SELECT id, name FROM test_table WHERE name = ?")?;
+    assert_eq!(statement.parameter_count(), 1);
+    let column_names = statement
+        .columns()
+        .iter()
+        .map(|column| column.name.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(column_names, ["id", "name"]);
+
+    // Select each row with the same prepared statement
+    for name in ["test1", "test2", "test3"] {
+        let result = statement.execute(&[name.into()])?;
+        assert_eq!(result.rows.len(), 1);
+        assert_eq!(
+            result.rows[0].get_value_by_name("name"),
+            Some(&dbc::Value::String(name.to_owned()))
+        );
+    }
+    drop(statement);
+
+    _cleanup_database(database)?;
+
+    Ok(())
+}
+
+pub(crate) async fn test_query_iter(mut database: dbc::Database) -> Result<(), Error> {
+    for name in ["test1", "test2", "test3"] {
+        database.execute_query_with_params(
+            "INSERT INTO test_table (name) VALUES (?)",
+            &[name.into()],
+        )?;
+    }
+
+    // Rows are read lazily, stopping early leaves the remaining rows unread
+    let mut cursor = database.query_iter("SELECT id, name FROM test_table ORDER BY id")?;
+    assert_eq!(cursor.columns().len(), 2);
+    let row = cursor.next().unwrap()?;
+    assert_eq!(
+        row.get_value_by_name("name"),
+        Some(&dbc::Value::String("test1".to_owned()))
+    );
+    drop(cursor);
+
+    let cursor = database.query_iter_with_params(
+        "SELECT name FROM test_table WHERE id > ? ORDER BY id",
+        &[1.into()],
+    )?;
+    let names = cursor
+        .map(|row| Ok(row?.get_value(0).cloned()))
+        .collect::<Result<Vec<_>, dbc::Error>>()?;
+    assert_eq!(
+        names,
+        [
+            Some(dbc::Value::String("test2".to_owned())),
+            Some(dbc::Value::String("test3".to_owned()))
+        ]
+    );
+
+    // Prepared statements can stream their rows as well
+    let mut statement = database.prepare("SELECT id FROM test_table WHERE id < ?")?;
+    assert_eq!(statement.query(&[3.into()])?.count(), 2);
+    drop(statement);
+
+    // The database is usable again once the cursor is dropped
+    let result = database.execute_query("SELECT * FROM test_table")?;
+    assert_eq!(result.rows.len(), 3);
+
+    _cleanup_database(database)?;
+
+    Ok(())
+}
+
+pub(crate) async fn test_expression_columns(mut database: dbc::Database) -> Result<(), Error> {
+    let insert_query = "INSERT INTO test_table (name) VALUES ('test1'), ('test2')";
+    database.execute_query(insert_query)?;
+
+    // Computed columns are not read from a table and may have no declared type
+    let query = "SELECT 1 + 1 AS two, COUNT(*) AS count FROM test_table";
+    let result = database.execute_query(query)?;
+    assert_eq!(result.rows.len(), 1);
+    let row = &result.rows[0];
+    assert_eq!(row.get_value_by_name("two"), Some(&dbc::Value::Int(2)));
+    assert_eq!(row.get_value_by_name("count"), Some(&dbc::Value::Int(2)));
+    for name in ["two", "count"] {
+        let column = row.get_column_by_name(name).unwrap();
+        assert!(
+            matches!(column.column_type, dbc::ColumnType::INT),
+            "{:?}",
+            column
+        );
+    }
+
+    _cleanup_database(database)?;
+
+    Ok(())
+}
+
+pub(crate) async fn test_typed_getters(mut database: dbc::Database) -> Result<(), Error> {
+    let insert_query = "INSERT INTO test_table (name) VALUES ('test1'), ('test2')";
+    database.execute_query(insert_query)?;
+
+    let query = "SELECT id, name, NULL AS note FROM test_table ORDER BY id";
+    let result = database.execute_query(query)?;
+    let row = &result.rows[1];
+    assert_eq!(row.get::<i64>(0)?, 2);
+    assert_eq!(row.get::<u8>("id")?, 2);
+    assert_eq!(row.get::<f64>("id")?, 2.0);
+    assert_eq!(row.get::<String>("name")?, "test2");
+    assert_eq!(row.get::<Vec<u8>>("name")?, b"test2");
+    assert_eq!(row.get::<Option<String>>("name")?, Some("test2".to_owned()));
+    assert_eq!(row.get::<Option<i64>>("note")?, None);
+    assert_eq!(row.get::<dbc::Value>("note")?, dbc::Value::NULL);
+
+    // Conversion errors name the column
+    let err = row.get::<i64>("name").unwrap_err();
+    assert!(matches!(err, dbc::Error::TypeConversion(_)), "{:?}", err);
+    assert!(err.to_string().contains("column name"), "{}", err);
+    let err = row.get::<String>("note").unwrap_err();
+    assert!(matches!(err, dbc::Error::TypeConversion(_)), "{:?}", err);
+    assert!(err.to_string().contains("column note"), "{}", err);
+    assert!(matches!(
+        row.get::<i64>("missing"),
+        Err(dbc::Error::Usage(_))
+    ));
+    assert!(matches!(row.get::<i64>(3), Err(dbc::Error::Usage(_))));
+
+    _cleanup_database(database)?;
+
+    Ok(())
+}
+
+#[cfg(feature = "derive")]
+#[derive(dbc::FromRow, Debug, PartialEq)]
+struct Person {
+    id: i64,
+    #[rdbc(rename = "name")]
+    label: String,
+    #[rdbc(flatten)]
+    details: PersonDetails,
+    #[rdbc(default)]
+    age: Option<i64>,
+    #[rdbc(default = "default_score")]
+    score: i64,
+}
+
+#[cfg(feature = "derive")]
+#[derive(dbc::FromRow, Debug, PartialEq)]
+struct PersonDetails {
+    note: Option<String>,
+}
+
+fn default_score() -> i64 {
+    10
+}
+
+#[cfg(feature = "derive")]
+pub(crate) async fn test_query_as(mut database: dbc::Database) -> Result<(), Error> {
+    let insert_query = "INSERT INTO test_table (name) VALUES ('test1'), ('test2')";
+    database.execute_query(insert_query)?;
+
+    let query = "SELECT id, name, NULL AS note FROM test_table WHERE id > ? ORDER BY id";
+    let people: Vec<Person> = database.query_as(query, &[1.into()])?;
+    assert_eq!(
+        people,
+        vec![Person {
+            id: 2,
+            label: "test2".to_owned(),
+            details: PersonDetails { note: None },
+            age: None,
+            score: 10,
+        }]
+    );
+
+    // Columns present in the row are read even when the field has a default
+    let query = "SELECT id, name, 'note' AS note, 3 AS score FROM test_table ORDER BY id";
+    let people = database.query_as::<Person>(query, &[])?;
+    assert_eq!(people.len(), 2);
+    assert_eq!(people[0].details.note.as_deref(), Some("note"));
+    assert_eq!(people[0].score, 3);
+
+    // A missing column without a default is an error
+    let err = database
+        .query_as::<Person>("SELECT id FROM test_table", &[])
+        .unwrap_err();
+    assert!(matches!(err, dbc::Error::Usage(_)), "{:?}", err);
+
+    _cleanup_database(database)?;
+
+    Ok(())
+}
+
+#[derive(serde::Deserialize, Debug, PartialEq)]
+struct NamedRow<'a> {
+    id: u32,
+    name: &'a str,
+    note: Option<String>,
+}
+
+pub(crate) async fn test_deserialize_rows(mut database: dbc::Database) -> Result<(), Error> {
+    let insert_query = "INSERT INTO test_table (name) VALUES ('test1'), ('test2')";
+    database.execute_query(insert_query)?;
+
+    let query = "SELECT id, name, NULL AS note FROM test_table ORDER BY id";
+    let result = database.execute_query(query)?;
+    let rows = result.deserialize_rows::<NamedRow>()?;
+    assert_eq!(
+        rows,
+        vec![
+            NamedRow {
+                id: 1,
+                name: "test1",
+                note: None
+            },
+            NamedRow {
+                id: 2,
+                name: "test2",
+                note: None
+            },
+        ]
+    );
+    let row: (i64, String, Option<String>) = result.rows[1].deserialize()?;
+    assert_eq!(row, (2, "test2".to_owned(), None));
+
+    // Conversion errors name the column
+    #[derive(serde::Deserialize, Debug)]
+    struct InvalidRow {
+        #[allow(dead_code)]
+        name: i64,
+    }
+    let err = result.rows[0].deserialize::<InvalidRow>().unwrap_err();
+    assert!(matches!(err, dbc::Error::TypeConversion(_)), "{:?}", err);
+    assert!(err.to_string().contains("column name"), "{}", err);
+
+    _cleanup_database(database)?;
+
+    Ok(())
+}
+
+pub(crate) async fn test_to_json(mut database: dbc::Database) -> Result<(), Error> {
+    let insert_query = "INSERT INTO test_table (name) VALUES ('test1'), ('test2')";
+    database.execute_query(insert_query)?;
+
+    let query = "SELECT id, name, NULL AS note FROM test_table ORDER BY id";
+    let result = database.execute_query(query)?;
+    let json: serde_json::Value = serde_json::from_str(&result.to_json(dbc::JsonFormat::Objects)?)?;
+    assert_eq!(
+        json,
+        serde_json::json!([
+            {"id": 1, "name": "test1", "note": null},
+            {"id": 2, "name": "test2", "note": null},
+        ])
+    );
+
+    let json: serde_json::Value =
+        serde_json::from_str(&result.to_json(dbc::JsonFormat::Columnar)?)?;
+    let names = json["columns"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|column| column["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["id", "name", "note"]);
+    assert_eq!(
+        json["rows"],
+        serde_json::json!([[1, "test1", null], [2, "test2", null]])
+    );
+
+    // The columns of an empty result are kept
+    let result = database.execute_query("SELECT id, name FROM test_table WHERE id < 0")?;
+    let json: serde_json::Value =
+        serde_json::from_str(&result.to_json(dbc::JsonFormat::Columnar)?)?;
+    assert_eq!(json["columns"].as_array().map(Vec::len), Some(2));
+    assert_eq!(json["rows"], serde_json::json!([]));
+
+    // The tagged format deserializes back into the same values
+    let result = database.execute_query(query)?;
+    let json = result.to_json(dbc::JsonFormat::Tagged)?;
+    let tagged: dbc::QueryResult = serde_json::from_str(&json)?;
+    assert_eq!(tagged.kind, dbc::StatementKind::Query);
+    assert_eq!(
+        tagged.rows[1].get_value_by_name("name"),
+        Some(&dbc::Value::String("test2".to_owned()))
+    );
+
+    _cleanup_database(database)?;
+
+    Ok(())
+}
+
+pub(crate) async fn test_query_and_serialize_raw(mut database: dbc::Database) -> Result<(), Error> {
+    let insert_query = "INSERT INTO test_table (name) VALUES ('test1'), ('test2')";
+    database.execute_query(insert_query)?;
+
+    let query = "SELECT id, name, NULL AS note FROM test_table ORDER BY id";
+    let bytes = database.execute_query_and_serialize_raw(query)?;
+    let decoded = dbc::QueryResult::from_bytes(&bytes)?;
+    let result = database.execute_query(query)?;
+    assert_eq!(decoded.rows.len(), 2);
+    assert_eq!(decoded.kind, dbc::StatementKind::Query);
+    for (decoded_row, row) in decoded.rows.iter().zip(&result.rows) {
+        for index in 0..3 {
+            assert_eq!(decoded_row.get_value(index), row.get_value(index));
+            assert_eq!(
+                decoded_row.get_column(index).map(|column| &column.name),
+                row.get_column(index).map(|column| &column.name)
+            );
+        }
+    }
+    assert_eq!(
+        serde_json::to_string(&decoded)?,
+        database.execute_query_and_serialize(query)?
+    );
+
+    // Results of other versions or cut short are rejected
+    let mut other_version = bytes.clone();
+    other_version[4] += 1;
+    let err = dbc::QueryResult::from_bytes(&other_version).unwrap_err();
+    assert!(err.to_string().contains("unsupported version"), "{}", err);
+    let err = dbc::QueryResult::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err();
+    assert!(matches!(err, dbc::Error::TypeConversion(_)), "{:?}", err);
+
+    _cleanup_database(database)?;
+
+    Ok(())
+}
+
+/// Run a query which fails after some of its rows have been read
+pub(crate) async fn test_mid_stream_error(
+    mut database: dbc::Database,
+    query: &str,
+) -> Result<(), Error> {
+    let mut rows = database.query_iter(query)?;
+    let mut read_row_count = 0;
+    let err = loop {
+        match rows.next() {
+            Some(Ok(_)) => read_row_count += 1,
+            Some(Err(err)) => break err,
+            None => panic!("The query did not fail"),
+        }
+    };
+    drop(rows);
+    assert!(
+        read_row_count < 5,
+        "{} rows read before {}",
+        read_row_count,
+        err
+    );
+    assert!(database.execute_query(query).is_err());
+
+    // The connection is still usable after errors
+    let result = database.execute_query("SELECT 1")?;
+    assert_eq!(result.rows[0].get_value(0), Some(&dbc::Value::Int(1)));
+
+    Ok(())
+}
+
+pub(crate) async fn test_statement_kind(mut database: dbc::Database) -> Result<(), Error> {
+    let query = "-- insert a row\n  insert INTO test_table (name) VALUES ('test1')";
+    let result = database.execute_query(query)?;
+    assert_eq!(result.kind, dbc::StatementKind::Insert);
+    assert_eq!(result.affected_row_count, 1);
+
+    // Rows are returned regardless of case, leading comments or common table expressions
+    let result = database.execute_query("/* all rows */ select id, name from test_table")?;
+    assert_eq!(result.kind, dbc::StatementKind::Query);
+    assert_eq!(result.rows.len(), 1);
+    let query = "WITH named AS (SELECT id, name FROM test_table) SELECT id, name FROM named";
+    let result = database.execute_query(query)?;
+    assert_eq!(result.kind, dbc::StatementKind::Query);
+    assert_eq!(result.rows.len(), 1);
+
+    let result = database.execute_query("UPDATE test_table SET name = 'updated'")?;
+    assert_eq!(result.kind, dbc::StatementKind::Update);
+    assert_eq!(result.affected_row_count, 1);
+    let result = database.execute_query("DELETE FROM test_table")?;
+    assert_eq!(result.kind, dbc::StatementKind::Delete);
+    assert_eq!(result.affected_row_count, 1);
+
+    let result = database.execute_query("DROP TABLE test_table")?;
+    assert_eq!(result.kind, dbc::StatementKind::Ddl);
+
+    Ok(())
+}
+
+pub(crate) async fn test_execute_script(mut database: dbc::Database) -> Result<(), Error> {
+    // Semicolons in strings and comments don't split statements
+    let script = "
+        -- seed the table; twice
+        INSERT INTO test_table (name) VALUES ('test;1');
+        /* and; again */
+        INSERT INTO test_table (name) VALUES ('test2');;
+        SELECT name FROM test_table ORDER BY id;
+    ";
+    let results = database.execute_script(script)?;
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].affected_row_count, 1);
+    assert_eq!(
+        results[2].rows[0].get_value(0),
+        Some(&dbc::Value::String("test;1".to_owned()))
+    );
+
+    // The script stops at the first failing statement, rolling back everything in a transaction
+    let script =
+        "INSERT INTO test_table (name) VALUES ('test3'); INSERT INTO missing_table VALUES (1)";
+    assert!(database.execute_script_in_transaction(script).is_err());
+    assert_eq!(
+        database
+            .execute_query("SELECT * FROM test_table")?
+            .rows
+            .len(),
+        2
+    );
+    assert!(database.execute_script(script).is_err());
+    assert_eq!(
+        database
+            .execute_query("SELECT * FROM test_table")?
+            .rows
+            .len(),
+        3
+    );
+
+    _cleanup_database(database)?;
+
+    Ok(())
+}
+
+pub(crate) async fn test_error_kinds(mut database: dbc::Database) -> Result<(), Error> {
+    database.execute_query("INSERT INTO test_table (id, name) VALUES (1, 'test1')")?;
+
+    let query = "INSERT INTO test_table (id, name) VALUES (1, 'test2')";
+    let err = database.execute_query(query).err().unwrap();
+    assert!(
+        matches!(
+            err,
+            dbc::Error::ConstraintViolation {
+                kind: dbc::ConstraintKind::Unique,
+                ..
+            }
+        ),
+        "{:?}",
+        err
+    );
+    // The backend error code and the driver error are kept
+    assert!(err.code().is_some() || err.sql_state().is_some());
+    assert!(std::error::Error::source(&err).is_some());
+
+    let query = "INSERT INTO test_table (id, name) VALUES (2, NULL)";
+    let err = database.execute_query(query).err().unwrap();
+    assert!(
+        matches!(
+            err,
+            dbc::Error::ConstraintViolation {
+                kind: dbc::ConstraintKind::NotNull,
+                ..
+            }
+        ),
+        "{:?}",
+        err
+    );
+
+    let err = database
+        .execute_query("SELEC * FROM test_table")
+        .err()
+        .unwrap();
+    assert!(matches!(err, dbc::Error::Syntax(_)), "{:?}", err);
+
+    // The connection is still usable after errors
+    let result = database.execute_query("SELECT * FROM test_table")?;
+    assert_eq!(result.rows.len(), 1);
+
+    _cleanup_database(database)?;
+
+    Ok(())
+}
+
+pub(crate) async fn test_transaction(mut database: dbc::Database) -> Result<(), Error> {
+    let insert_query = "INSERT INTO test_table (name) VALUES (?)";
+    let select_query = "SELECT * FROM test_table";
+
+    // Insert a row in a committed transaction
+    let mut transaction = database.transaction()?;
+    let result = transaction.execute_query_with_params(insert_query, &["test1".into()])?;
+    assert_eq!(result.affected_row_count, 1);
+    let result = transaction.execute_query(select_query)?;
+    assert_eq!(result.rows.len(), 1);
+    transaction.commit()?;
+    let result = database.execute_query(select_query)?;
+    assert_eq!(result.rows.len(), 1);
+
+    // Insert a row in a transaction that is rolled back explicitly
+    let mut transaction = database.transaction()?;
+    let mut statement = transaction.prepare(insert_query)?;
+    statement.execute(&["test2".into()])?;
+    drop(statement);
+    let result = transaction.execute_query(select_query)?;
+    assert_eq!(result.rows.len(), 2);
+    transaction.rollback()?;
+    let result = database.execute_query(select_query)?;
+    assert_eq!(result.rows.len(), 1);
+
+    // Insert a row in a transaction that is dropped without being committed
+    let mut transaction = database.transaction()?;
+    let insert_query = "INSERT INTO test_table (name) VALUES (:name)";
+    transaction.execute_query_with_named_params(insert_query, &[("name", "test3".into())])?;
+    drop(transaction);
+    let result = database.execute_query(select_query)?;
+    assert_eq!(result.rows.len(), 1);
+    assert_eq!(
+        result.rows[0].get_value_by_name("name"),
+        Some(&dbc::Value::String("test1".to_owned()))
+    );
+
+    _cleanup_database(database)?;
+
+    Ok(())
+}
+
+pub(crate) async fn test_savepoint(mut database: dbc::Database) -> Result<(), Error> {
+    let insert_query = "INSERT INTO test_table (name) VALUES (?)";
+    let mut transaction = database.transaction()?;
+    transaction.execute_query_with_params(insert_query, &["test1".into()])?;
+
+    // Keep the rows of a released savepoint, but not those of a nested one rolled back
+    let mut savepoint = transaction.savepoint("first")?;
+    savepoint.execute_query_with_params(insert_query, &["test2".into()])?;
+    let mut nested_savepoint = savepoint.savepoint("nested")?;
+    nested_savepoint.execute_query_with_params(insert_query, &["test3".into()])?;
+    nested_savepoint.rollback()?;
+    savepoint.commit()?;
+
+    // Discard the rows of a savepoint dropped without being committed
+    let mut savepoint = transaction.savepoint("second")?;
+    savepoint.execute_query_with_params(insert_query, &["test4".into()])?;
+    drop(savepoint);
+
+    // Savepoint names must be plain identifiers
+    assert!(transaction
+        .savepoint("second; DROP TABLE test_table")
+        .is_err());
+
+    transaction.commit()?;
+
+    // Verify that only the rows outside of the rolled back savepoints remain
+    let result = database.execute_query("SELECT * FROM test_table ORDER BY id")?;
+    let names = result
+        .rows
+        .iter()
+        .map(|row| row.get_value_by_name("name").cloned())
+        .collect::<Vec<Option<dbc::Value>>>();
+    assert_eq!(
+        names,
+        [
+            Some(dbc::Value::String("test1".to_owned())),
+            Some(dbc::Value::String("test2".to_owned())),
+        ]
+    );
+
+    _cleanup_database(database)?;
+
+    Ok(())
+}
+
+pub(crate) async fn test_async_database(database: dbc::AsyncDatabase) -> Result<(), Error> {
+    use dbc::AsyncConnection;
+
+    let result = database
+        .execute_query_with_params(
+            "INSERT INTO test_table (name) VALUES (?)",
+            &["test1".into()],
+        )
+        .await?;
+    assert_eq!(result.affected_row_count, 1);
+    database
+        .execute_query_with_named_params(
+            "INSERT INTO test_table (name) VALUES (:name)",
+            &[("name", "test2".into())],
+        )
+        .await?;
+
+    // Queries submitted concurrently are run one after the other on the connection thread
+    let params = [2.into()];
+    let (first, second) = tokio::join!(
+        database.execute("SELECT name FROM test_table WHERE id = 1"),
+        database.execute_with_params("SELECT name FROM test_table WHERE id = ?", &params),
+    );
+    assert_eq!(
+        first?.rows[0].get_value(0),
+        Some(&dbc::Value::String("test1".to_owned()))
+    );
+    assert_eq!(
+        second?.rows[0].get_value(0),
+        Some(&dbc::Value::String("test2".to_owned()))
+    );
+
+    // Anything else the blocking Database supports can be run on the connection thread
+    let row_count = database
+        .run(|database| {
+            let mut transaction = database.transaction()?;
+            transaction.execute_query("DELETE FROM test_table")?;
+            transaction.rollback()?;
+            Ok(database
+                .execute_query("SELECT * FROM test_table")?
+                .rows
+                .len())
+        })
+        .await?;
+    assert_eq!(row_count, 2);
+
+    database
+        .run(|database| {
+            database.execute_query("DROP TABLE IF EXISTS test_table")?;
+            Ok(())
+        })
+        .await?;
+
+    Ok(())
+}
+
+pub(crate) async fn test_pool(url: &str) -> Result<(), Error> {
+    let pool = dbc::Pool::builder()
+        .min_size(1)
+        .max_size(2)
+        .checkout_timeout(std::time::Duration::from_millis(100))
+        .build(url)?;
+    assert_eq!(pool.size(), 1);
+
+    // Connections are opened on demand up to the maximum size
+    let mut first = pool.get()?;
+    first.ping()?;
+    let second = pool.get()?;
+    assert_eq!(pool.size(), 2);
+    assert!(matches!(pool.get(), Err(dbc::Error::Timeout(_))));
+
+    // Dropped connections are returned to the pool and reused
+    drop(first);
+    let third = pool.get()?;
+    assert_eq!(pool.size(), 2);
+    drop(second);
+    drop(third);
+    assert_eq!(pool.idle_count(), 2);
+
+    // The pool can be shared between threads
+    let handles = (0..4)
+        .map(|_| {
+            let pool = pool.clone();
+            std::thread::spawn(move || pool.get()?.ping())
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        handle.join().unwrap()?;
+    }
+    assert_eq!(pool.size(), 2);
+
+    // Idle connections are closed after the idle timeout, down to the minimum size
+    let pool = dbc::Pool::builder()
+        .min_size(1)
+        .idle_timeout(Some(std::time::Duration::from_millis(50)))
+        .build(url)?;
+    let connections = [pool.get()?, pool.get()?];
+    drop(connections);
+    assert_eq!(pool.size(), 2);
+    std::thread::sleep(std::time::Duration::from_millis(60));
+    drop(pool.get()?);
+    assert_eq!(pool.size(), 1);
+
+    // They are also closed while the pool is not used
+    let connections = [pool.get()?, pool.get()?];
+    drop(connections);
+    assert_eq!(pool.idle_count(), 2);
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    assert_eq!(pool.idle_count(), 1);
+    assert_eq!(pool.size(), 1);
+
+    Ok(())
+}
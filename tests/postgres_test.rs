@@ -0,0 +1,491 @@
+#![cfg(feature = "postgres")]
+
+use rdbc2::dbc;
+
+mod common;
+
+type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+fn _get_postgres_connection_url() -> String {
+    if std::env::var("POSTGRES_DATABASE_URL").is_ok() {
+        std::env::var("POSTGRES_DATABASE_URL").unwrap()
+    } else {
+        panic!("Please set the environment variable POSTGRES_DATABASE_URL to a valid PostgreSQL connection string.");
+    }
+}
+
+fn _prepare_postgres_database() -> Result<dbc::Database, Error> {
+    let url = _get_postgres_connection_url();
+    let mut database = dbc::Database::new(&url)?;
+    let query = "DROP TABLE IF EXISTS test_table";
+    database.execute_query(query)?;
+    // Create a test table with two rows
+    let query = "CREATE TABLE test_table (id SERIAL PRIMARY KEY, name VARCHAR(255) NOT NULL)";
+    database.execute_query(query)?;
+    Ok(database)
+}
+
+// The synchronous postgres client drives its own runtime, so these tests must not run inside tokio
+#[test]
+#[serial_test::serial]
+fn test_postgres_simple_query() -> Result<(), Error> {
+    let database = _prepare_postgres_database()?;
+    futures::executor::block_on(common::test_simple_query(database))
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_query_with_params() -> Result<(), Error> {
+    let database = _prepare_postgres_database()?;
+    futures::executor::block_on(common::test_query_with_params(database))
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_query_with_params_and_serialize() -> Result<(), Error> {
+    let database = _prepare_postgres_database()?;
+    futures::executor::block_on(common::test_query_with_params_and_serialize(database))
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_query_with_params_escaping() -> Result<(), Error> {
+    let database = _prepare_postgres_database()?;
+    futures::executor::block_on(common::test_query_with_params_escaping(database))
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_query_with_typed_params() -> Result<(), Error> {
+    let mut database = _prepare_postgres_database()?;
+    database.execute_query("DROP TABLE IF EXISTS typed_table")?;
+    database.execute_query("CREATE TABLE typed_table (id INT PRIMARY KEY, amount DOUBLE PRECISION, data BYTEA, note TEXT)")?;
+    futures::executor::block_on(common::test_query_with_typed_params(database))
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_query_with_named_params() -> Result<(), Error> {
+    let database = _prepare_postgres_database()?;
+    futures::executor::block_on(common::test_query_with_named_params(database))
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_prepared_statement() -> Result<(), Error> {
+    let database = _prepare_postgres_database()?;
+    futures::executor::block_on(common::test_prepared_statement(database))
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_query_iter() -> Result<(), Error> {
+    let database = _prepare_postgres_database()?;
+    futures::executor::block_on(common::test_query_iter(database))
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_expression_columns() -> Result<(), Error> {
+    let database = _prepare_postgres_database()?;
+    futures::executor::block_on(common::test_expression_columns(database))
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_typed_getters() -> Result<(), Error> {
+    let database = _prepare_postgres_database()?;
+    futures::executor::block_on(common::test_typed_getters(database))
+}
+
+#[cfg(feature = "derive")]
+#[test]
+#[serial_test::serial]
+fn test_postgres_query_as() -> Result<(), Error> {
+    let database = _prepare_postgres_database()?;
+    futures::executor::block_on(common::test_query_as(database))
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_deserialize_rows() -> Result<(), Error> {
+    let database = _prepare_postgres_database()?;
+    futures::executor::block_on(common::test_deserialize_rows(database))
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_to_json() -> Result<(), Error> {
+    let database = _prepare_postgres_database()?;
+    futures::executor::block_on(common::test_to_json(database))
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_query_and_serialize_raw() -> Result<(), Error> {
+    let database = _prepare_postgres_database()?;
+    futures::executor::block_on(common::test_query_and_serialize_raw(database))
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_mid_stream_error() -> Result<(), Error> {
+    let database = _prepare_postgres_database()?;
+    // Division by zero on the third row
+    let query = "SELECT 10 / (x - 3) AS value FROM generate_series(1, 5) AS x";
+    futures::executor::block_on(common::test_mid_stream_error(database, query))
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_statement_kind() -> Result<(), Error> {
+    let database = _prepare_postgres_database()?;
+    futures::executor::block_on(common::test_statement_kind(database))
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_execute_script() -> Result<(), Error> {
+    let database = _prepare_postgres_database()?;
+    futures::executor::block_on(common::test_execute_script(database))
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_error_kinds() -> Result<(), Error> {
+    let database = _prepare_postgres_database()?;
+    futures::executor::block_on(common::test_error_kinds(database))
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_error_message() -> Result<(), Error> {
+    let mut database = _prepare_postgres_database()?;
+    database.execute_query("INSERT INTO test_table (id, name) VALUES (1, 'test1')")?;
+    let err = database
+        .execute_query("INSERT INTO test_table (id, name) VALUES (1, 'test2')")
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "duplicate key value violates unique constraint \"test_table_pkey\"\n\
+        DETAIL: Key (id)=(1) already exists."
+    );
+    assert_eq!(err.database_error().unwrap().sql_state(), Some("23505"));
+
+    let err = database
+        .execute_query("SELECT nme FROM test_table")
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "column \"nme\" does not exist\nHINT: Perhaps you meant to reference the column \"test_table.name\"."
+    );
+    Ok(())
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_jsonb_question_mark_operators() -> Result<(), Error> {
+    let mut database = _prepare_postgres_database()?;
+    // Queries without parameters are sent unchanged
+    let query = "SELECT '{\"a\":1}'::jsonb ? 'a' AS has_a, \
+        '{\"a\":1}'::jsonb ?| array['a', 'b'] AS has_any, \
+        '{\"a\":1}'::jsonb ?& array['a', 'b'] AS has_all";
+    let result = database.execute_query(query)?;
+    assert!(result.rows[0].get::<bool>("has_a")?);
+    assert!(result.rows[0].get::<bool>("has_any")?);
+    assert!(!result.rows[0].get::<bool>("has_all")?);
+    let mut cursor = database.query_iter(query)?;
+    assert!(cursor.next().unwrap()?.get::<bool>("has_a")?);
+    drop(cursor);
+
+    // Queries with numbered placeholders keep their `?` operators
+    let query = "SELECT '{\"a\":1}'::jsonb ? $1 AS has_key";
+    let result = database.execute_query_with_params(query, &["a".into()])?;
+    assert!(result.rows[0].get::<bool>("has_key")?);
+    let mut statement = database.prepare(query)?;
+    let result = statement.execute(&["b".into()])?;
+    assert!(!result.rows[0].get::<bool>("has_key")?);
+    Ok(())
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_transaction() -> Result<(), Error> {
+    let database = _prepare_postgres_database()?;
+    futures::executor::block_on(common::test_transaction(database))
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_savepoint() -> Result<(), Error> {
+    let database = _prepare_postgres_database()?;
+    futures::executor::block_on(common::test_savepoint(database))
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_pool() -> Result<(), Error> {
+    futures::executor::block_on(common::test_pool(&_get_postgres_connection_url()))
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_insert_returning() -> Result<(), Error> {
+    let mut database = _prepare_postgres_database()?;
+    let query = "INSERT INTO test_table (name) VALUES ('test1'), ('test2') RETURNING id";
+    let result = database.execute_query(query)?;
+    assert_eq!(result.kind, dbc::StatementKind::Insert);
+    assert_eq!(result.affected_row_count, 2);
+    assert_eq!(result.rows.len(), 2);
+    Ok(())
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_execute_script_with_function() -> Result<(), Error> {
+    let mut database = _prepare_postgres_database()?;
+    let script = "
+        CREATE OR REPLACE FUNCTION rename_row() RETURNS trigger AS $body$
+        BEGIN
+            NEW.name := 'renamed';
+            RETURN NEW;
+        END;
+        $body$ LANGUAGE plpgsql;
+        CREATE TRIGGER rename_insert BEFORE INSERT ON test_table FOR EACH ROW EXECUTE FUNCTION rename_row();
+        INSERT INTO test_table (name) VALUES ('test1');
+    ";
+    let results = database.execute_script(script)?;
+    assert_eq!(results.len(), 3);
+    let result = database.execute_query("SELECT name FROM test_table")?;
+    assert_eq!(
+        result.rows[0].get_value(0),
+        Some(&dbc::Value::String("renamed".to_owned()))
+    );
+    database.execute_query("DROP TABLE test_table")?;
+    database.execute_query("DROP FUNCTION rename_row")?;
+    Ok(())
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_connect_options() -> Result<(), Error> {
+    // Options parsed from a URL connect the same way once rendered back
+    let options = dbc::PostgresOptions::from_url(&_get_postgres_connection_url())?;
+    let mut database = dbc::Database::new(&options.to_string())?;
+    database.ping()?;
+    let mut database =
+        dbc::Database::connect(options.connect_timeout(std::time::Duration::from_secs(5)))?;
+    database.ping()?;
+
+    let options = dbc::PostgresOptions::new()
+        .host("::1")
+        .user("user")
+        .password("p@ss w/rd?")
+        .database("shop")
+        .ssl_mode(dbc::PostgresSslMode::Disable)
+        .param("application_name", "rdbc2 tests");
+    let url = "postgres://user:p%40ss%20w%2Frd%3F@[::1]:5432/shop?sslmode=disable&application_name=rdbc2%20tests";
+    assert_eq!(options.to_string(), url);
+    assert_eq!(dbc::PostgresOptions::from_url(url)?, options);
+
+    Ok(())
+}
+
+// Unlike the blocking client, AsyncDatabase runs the connection on its own thread and can be
+// used inside tokio
+#[tokio::test]
+#[serial_test::serial]
+async fn test_postgres_async_database() -> Result<(), Error> {
+    let database = dbc::AsyncDatabase::new(&_get_postgres_connection_url()).await?;
+    database
+        .run(|database| {
+            database.execute_query("DROP TABLE IF EXISTS test_table")?;
+            database.execute_query(
+                "CREATE TABLE test_table (id SERIAL PRIMARY KEY, name VARCHAR(255) NOT NULL)",
+            )?;
+            Ok(())
+        })
+        .await?;
+    common::test_async_database(database).await
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_type_mapping() -> Result<(), Error> {
+    let mut database = _prepare_postgres_database()?;
+    let query = "SELECT true AS flag, 42::int2 AS small, 1234567890123::int8 AS big, 1.5::float4 AS single, \
+        -12345.6789::numeric(12, 4) AS amount, 0.001::numeric AS fraction, 'text'::text AS label, '\\x0102'::bytea AS data, \
+        '2023-05-17'::date AS day, '2023-05-17 13:45:30.25'::timestamp AS moment, '-1 day 02:03:04'::interval AS span, \
+        '8c0f6ab1-4c22-4a1e-9a5c-2b2f3c4d5e6f'::uuid AS uuid, '{\"a\": 1}'::jsonb AS doc, NULL::int4 AS nothing, \
+        '{1,-1,0.5}'::line AS line, '[(0,0),(1,2)]'::lseg AS segment, '(0,0),(1,2)'::box AS area, \
+        '[(0,0),(1,1)]'::path AS route, '((0,0),(1,1),(1,0))'::path AS loop, \
+        '((0,0),(1,1),(1,0))'::polygon AS shape, '<(1,2),1.5>'::circle AS ring, \
+        '[1,5]'::int4range AS ids, '(,2.5]'::numrange AS amounts, 'empty'::int8range AS none, \
+        '12:00+05'::timetz AS noon_east, '12:00:01.5-03:30'::timetz AS noon_west, \
+        12.34::money AS price, (-0.05)::money AS refund";
+    let result = database.execute_query(query)?;
+    assert_eq!(result.rows.len(), 1);
+
+    let row = &result.rows[0];
+    let expected = [
+        ("flag", dbc::Value::Bool(true)),
+        ("small", dbc::Value::Int(42)),
+        ("big", dbc::Value::Int(1234567890123)),
+        ("single", dbc::Value::Float(1.5)),
+        ("amount", dbc::Value::String("-12345.6789".to_owned())),
+        ("fraction", dbc::Value::String("0.001".to_owned())),
+        ("label", dbc::Value::String("text".to_owned())),
+        ("data", dbc::Value::Bytes(vec![1, 2])),
+        ("day", dbc::Value::Date(2023, 5, 17, 0, 0, 0, 0)),
+        ("moment", dbc::Value::Date(2023, 5, 17, 13, 45, 30, 250000)),
+        ("span", dbc::Value::Time(true, 0, 21, 56, 56, 0)),
+        (
+            "uuid",
+            dbc::Value::String("8c0f6ab1-4c22-4a1e-9a5c-2b2f3c4d5e6f".to_owned()),
+        ),
+        ("doc", dbc::Value::String(r#"{"a": 1}"#.to_owned())),
+        ("nothing", dbc::Value::NULL),
+        ("line", dbc::Value::String("{1,-1,0.5}".to_owned())),
+        ("segment", dbc::Value::String("[(0,0),(1,2)]".to_owned())),
+        ("area", dbc::Value::String("(1,2),(0,0)".to_owned())),
+        ("route", dbc::Value::String("[(0,0),(1,1)]".to_owned())),
+        ("loop", dbc::Value::String("((0,0),(1,1),(1,0))".to_owned())),
+        (
+            "shape",
+            dbc::Value::String("((0,0),(1,1),(1,0))".to_owned()),
+        ),
+        ("ring", dbc::Value::String("<(1,2),1.5>".to_owned())),
+        ("ids", dbc::Value::String("[1,6)".to_owned())),
+        ("amounts", dbc::Value::String("(,2.5]".to_owned())),
+        ("none", dbc::Value::String("empty".to_owned())),
+        // The zone offset of a timetz is kept
+        ("noon_east", dbc::Value::String("12:00:00+05".to_owned())),
+        (
+            "noon_west",
+            dbc::Value::String("12:00:01.5-03:30".to_owned()),
+        ),
+        // money is an amount in currency units rather than cents
+        ("price", dbc::Value::String("12.34".to_owned())),
+        ("refund", dbc::Value::String("-0.05".to_owned())),
+    ];
+    for (name, value) in expected {
+        assert_eq!(row.get_value_by_name(name), Some(&value), "column {}", name);
+    }
+
+    Ok(())
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_text_form_values() -> Result<(), Error> {
+    let mut database = _prepare_postgres_database()?;
+    let query =
+        "SELECT ARRAY[1, 2, NULL]::int[] AS numbers, ARRAY[['a', 'b c'], ['', 'd\"e']] AS names, \
+        '{}'::int[] AS empty, ARRAY[true, false] AS flags, ARRAY['\\x01ff'::bytea] AS blobs, \
+        '192.168.0.1'::inet AS host, '10.0.0.0/8'::cidr AS network, '::1/64'::inet AS v6, \
+        '08:00:2b:01:02:03'::macaddr AS mac, B'1011'::varbit AS bits, '(1.5,2)'::point AS point";
+    let result = database.execute_query(query)?;
+    let row = &result.rows[0];
+    let expected = [
+        ("numbers", "{1,2,NULL}"),
+        ("names", r#"{{a,"b c"},{"","d\"e"}}"#),
+        ("empty", "{}"),
+        ("flags", "{t,f}"),
+        ("blobs", r#"{"\\x01ff"}"#),
+        ("host", "192.168.0.1"),
+        ("network", "10.0.0.0/8"),
+        ("v6", "::1/64"),
+        ("mac", "08:00:2b:01:02:03"),
+        ("bits", "1011"),
+        ("point", "(1.5,2)"),
+    ];
+    for (name, text) in expected {
+        assert_eq!(row.get::<String>(name)?, text, "column {}", name);
+    }
+
+    // Types without a mapping are read as their binary encoding instead of failing the query
+    let result = database.execute_query("SELECT 'a fat cat'::tsvector AS document, 1 AS id")?;
+    let row = &result.rows[0];
+    assert!(matches!(row.get_value(0), Some(dbc::Value::Bytes(bytes)) if !bytes.is_empty()));
+    assert_eq!(row.get::<i64>("id")?, 1);
+    Ok(())
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_bytes_param_as_text() -> Result<(), Error> {
+    let mut database = _prepare_postgres_database()?;
+    let result = database.execute_query_with_params(
+        "SELECT $1::text AS name",
+        &[dbc::Value::Bytes(b"abc".to_vec())],
+    )?;
+    assert_eq!(result.rows[0].get::<String>("name")?, "abc");
+
+    // Bytes that are not UTF-8 are rejected rather than bound as a lossy text
+    let err = database
+        .execute_query_with_params(
+            "SELECT $1::text AS name",
+            &[dbc::Value::Bytes(vec![0xff, 0x00])],
+        )
+        .unwrap_err();
+    let cause = std::iter::successors(std::error::Error::source(&err), |err| err.source())
+        .last()
+        .map(ToString::to_string)
+        .unwrap_or_default();
+    assert!(
+        cause.starts_with("Cannot bind bytes to a text parameter"),
+        "{}",
+        cause
+    );
+    Ok(())
+}
+
+#[test]
+#[serial_test::serial]
+fn test_postgres_json_values() -> Result<(), Error> {
+    let mut database = _prepare_postgres_database()?;
+    let query =
+        "SELECT '\\x0102'::bytea AS data, 1.5::float8 AS amount, '2023-05-17'::date AS day, \
+        '2023-05-17 13:45:30.25'::timestamp AS moment, '-1 day 02:03:04'::interval AS span";
+    let result = database.execute_query(query)?;
+    let json: serde_json::Value = serde_json::from_str(&result.to_json(dbc::JsonFormat::Objects)?)?;
+    assert_eq!(
+        json,
+        serde_json::json!([{
+            "data": "AQI",
+            "amount": 1.5,
+            "day": "2023-05-17",
+            "moment": "2023-05-17T13:45:30.250000",
+            "span": "-21:56:56",
+        }])
+    );
+    Ok(())
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+#[serial_test::serial]
+fn test_postgres_chrono_values() -> Result<(), Error> {
+    use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Utc};
+
+    let mut database = _prepare_postgres_database()?;
+    let query = "SELECT '2023-05-17'::date AS day, '2023-05-17 13:45:30.25'::timestamp AS moment, \
+        '2023-05-17 13:45:30.25+02'::timestamptz AS instant, '-1 day 02:03:04'::interval AS span";
+    let result = database.execute_query(query)?;
+    let row = &result.rows[0];
+    let moment = NaiveDate::from_ymd_opt(2023, 5, 17)
+        .unwrap()
+        .and_hms_milli_opt(13, 45, 30, 250)
+        .unwrap();
+    assert_eq!(row.get::<NaiveDate>("day")?, moment.date());
+    assert_eq!(row.get::<NaiveDateTime>("moment")?, moment);
+    assert_eq!(
+        row.get::<DateTime<Utc>>("instant")?,
+        (moment - Duration::hours(2)).and_utc()
+    );
+    assert_eq!(
+        row.get::<Duration>("span")?,
+        -Duration::hours(21) - Duration::minutes(56) - Duration::seconds(56)
+    );
+    Ok(())
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "sqlite")]
+
 use rdbc2::dbc;
 
 mod common;
@@ -39,3 +41,498 @@ async fn test_sqlite_query_with_params_and_serialize() -> Result<(), Error> {
     let database = _prepare_sqlite_database()?;
     common::test_query_with_params_and_serialize(database).await
 }
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_query_with_params_escaping() -> Result<(), Error> {
+    let database = _prepare_sqlite_database()?;
+    common::test_query_with_params_escaping(database).await
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_query_with_typed_params() -> Result<(), Error> {
+    let mut database = _prepare_sqlite_database()?;
+    database.execute_query("DROP TABLE IF EXISTS typed_table")?;
+    database.execute_query(
+        "CREATE TABLE typed_table (id INTEGER PRIMARY KEY, amount REAL, data BLOB, note TEXT)",
+    )?;
+    common::test_query_with_typed_params(database).await
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_query_with_named_params() -> Result<(), Error> {
+    let database = _prepare_sqlite_database()?;
+    common::test_query_with_named_params(database).await
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_prepared_statement() -> Result<(), Error> {
+    let database = _prepare_sqlite_database()?;
+    common::test_prepared_statement(database).await
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_query_iter() -> Result<(), Error> {
+    let database = _prepare_sqlite_database()?;
+    common::test_query_iter(database).await
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_query_iter_dropped_early() -> Result<(), Error> {
+    let mut database = _prepare_sqlite_database()?;
+    database.execute_query("INSERT INTO test_table (name) VALUES ('test1'), ('test2')")?;
+    for _ in 0..3 {
+        let mut cursor = database.query_iter("SELECT name FROM test_table ORDER BY id")?;
+        assert!(cursor.next().is_some());
+        drop(cursor);
+    }
+
+    // A statement still reading rows would lock the table
+    database.execute_query("DROP TABLE test_table")?;
+    Ok(())
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_expression_columns() -> Result<(), Error> {
+    let mut database = _prepare_sqlite_database()?;
+    // Columns are typed by their first non-NULL value
+    let query = "SELECT CASE WHEN id = 1 THEN NULL ELSE id END AS value FROM \
+        (SELECT 1 AS id UNION ALL SELECT 2 UNION ALL SELECT 3)";
+    let result = database.execute_query(query)?;
+    let column_types = result
+        .rows
+        .iter()
+        .map(|row| row.get_column(0).unwrap().column_type.clone())
+        .collect::<Vec<dbc::ColumnType>>();
+    assert!(matches!(
+        column_types[..],
+        [
+            dbc::ColumnType::UNKNOWN,
+            dbc::ColumnType::INT,
+            dbc::ColumnType::INT
+        ]
+    ));
+    common::test_expression_columns(database).await
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_typed_getters() -> Result<(), Error> {
+    let database = _prepare_sqlite_database()?;
+    common::test_typed_getters(database).await
+}
+
+// SQLite has no date types, dates are read from their text
+#[cfg(feature = "chrono")]
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_chrono_values() -> Result<(), Error> {
+    use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+    let mut database = _prepare_sqlite_database()?;
+    let query =
+        "SELECT '2023-05-17' AS day, '2023-05-17 13:45:30.25' AS moment, '13:45:30' AS time";
+    let result = database.execute_query(query)?;
+    let row = &result.rows[0];
+    let day = NaiveDate::from_ymd_opt(2023, 5, 17).unwrap();
+    assert_eq!(row.get::<NaiveDate>("day")?, day);
+    assert_eq!(
+        row.get::<NaiveDateTime>("moment")?,
+        day.and_hms_milli_opt(13, 45, 30, 250).unwrap()
+    );
+    assert_eq!(
+        row.get::<NaiveTime>("time")?,
+        NaiveTime::from_hms_opt(13, 45, 30).unwrap()
+    );
+    // A date with a time of day is not truncated
+    assert!(matches!(
+        row.get::<NaiveDate>("moment"),
+        Err(dbc::Error::TypeConversion(_))
+    ));
+    Ok(())
+}
+
+#[cfg(feature = "time")]
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_time_values() -> Result<(), Error> {
+    use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time};
+
+    let mut database = _prepare_sqlite_database()?;
+    let query =
+        "SELECT '2023-05-17' AS day, '2023-05-17T13:45:30.25' AS moment, '13:45:30' AS time";
+    let result = database.execute_query(query)?;
+    let row = &result.rows[0];
+    let day = Date::from_calendar_date(2023, Month::May, 17)?;
+    let moment = PrimitiveDateTime::new(day, Time::from_hms_milli(13, 45, 30, 250)?);
+    assert_eq!(row.get::<Date>("day")?, day);
+    assert_eq!(row.get::<PrimitiveDateTime>("moment")?, moment);
+    assert_eq!(row.get::<OffsetDateTime>("moment")?, moment.assume_utc());
+    assert_eq!(row.get::<Time>("time")?, Time::from_hms(13, 45, 30)?);
+    Ok(())
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_stream_encoding() -> Result<(), Error> {
+    let mut database = _prepare_sqlite_database()?;
+    let query = "WITH RECURSIVE numbers(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM numbers WHERE x < 1000) \
+        SELECT x AS id, 'name ' || x AS name, x / 3.0 AS ratio FROM numbers";
+    // Rows are encoded as they are read from the database
+    let cursor = database.query_iter(query)?;
+    let mut encoder = dbc::Encoder::new(Vec::new(), cursor.columns())?;
+    for row in cursor {
+        encoder.encode_row(&row?)?;
+    }
+    let bytes = encoder.finish(0, dbc::StatementKind::Query)?;
+
+    let decoder = dbc::Decoder::new(&bytes[..])?;
+    let names = decoder
+        .columns()
+        .iter()
+        .map(|column| column.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["id", "name", "ratio"]);
+    let rows = decoder.collect::<Result<Vec<_>, _>>()?;
+    let result = database.execute_query(query)?;
+    assert_eq!(rows.len(), 1000);
+    for (decoded, row) in rows.iter().zip(&result.rows) {
+        for index in 0..3 {
+            assert_eq!(decoded.get_value(index), row.get_value(index));
+        }
+    }
+    Ok(())
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_text_values() -> Result<(), Error> {
+    let mut database = dbc::Database::new(SQLITE_DATABASE_URL)?;
+    let query = "SELECT 'text' AS valid, CAST(x'ff00' AS TEXT) AS invalid, x'ff00' AS blob";
+    let result = database.execute_query(query)?;
+    let row = &result.rows[0];
+    assert_eq!(
+        row.get_value_by_name("valid"),
+        Some(&dbc::Value::String("text".to_owned()))
+    );
+    // Text which is not valid UTF-8 is kept as bytes
+    assert_eq!(
+        row.get_value_by_name("invalid"),
+        Some(&dbc::Value::Bytes(vec![0xff, 0]))
+    );
+    assert_eq!(
+        row.get_value_by_name("blob"),
+        Some(&dbc::Value::Bytes(vec![0xff, 0]))
+    );
+    Ok(())
+}
+
+#[cfg(feature = "derive")]
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_query_as() -> Result<(), Error> {
+    let database = _prepare_sqlite_database()?;
+    common::test_query_as(database).await
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_deserialize_rows() -> Result<(), Error> {
+    let database = _prepare_sqlite_database()?;
+    common::test_deserialize_rows(database).await
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_to_json() -> Result<(), Error> {
+    let database = _prepare_sqlite_database()?;
+    common::test_to_json(database).await
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_query_and_serialize_raw() -> Result<(), Error> {
+    let database = _prepare_sqlite_database()?;
+    common::test_query_and_serialize_raw(database).await
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_mid_stream_error() -> Result<(), Error> {
+    let database = _prepare_sqlite_database()?;
+    // abs() overflows on the third row
+    let query = "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n WHERE x < 5) \
+        SELECT CASE WHEN x = 3 THEN abs(-9223372036854775807 - 1) ELSE x END AS value FROM n";
+    common::test_mid_stream_error(database, query).await
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_statement_kind() -> Result<(), Error> {
+    let database = _prepare_sqlite_database()?;
+    common::test_statement_kind(database).await
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_execute_script() -> Result<(), Error> {
+    let database = _prepare_sqlite_database()?;
+    common::test_execute_script(database).await
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_error_kinds() -> Result<(), Error> {
+    let database = _prepare_sqlite_database()?;
+    common::test_error_kinds(database).await
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_transaction() -> Result<(), Error> {
+    let database = _prepare_sqlite_database()?;
+    common::test_transaction(database).await
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_savepoint() -> Result<(), Error> {
+    let database = _prepare_sqlite_database()?;
+    common::test_savepoint(database).await
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_async_database() -> Result<(), Error> {
+    let database = _prepare_sqlite_database()?;
+    common::test_async_database(dbc::AsyncDatabase::from(database)).await
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_pool() -> Result<(), Error> {
+    common::test_pool(SQLITE_DATABASE_URL).await
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_row_returning_statements() -> Result<(), Error> {
+    let mut database = _prepare_sqlite_database()?;
+
+    let query = "INSERT INTO test_table (name) VALUES ('test1') RETURNING id, name";
+    let result = database.execute_query(query)?;
+    assert_eq!(result.kind, dbc::StatementKind::Insert);
+    assert_eq!(result.affected_row_count, 1);
+    assert_eq!(
+        result.rows[0].get_value_by_name("name"),
+        Some(&dbc::Value::String("test1".to_owned()))
+    );
+
+    let result = database.execute_query("PRAGMA table_info(test_table)")?;
+    assert_eq!(result.kind, dbc::StatementKind::Query);
+    assert_eq!(result.rows.len(), 2);
+
+    let result = database.execute_query("VALUES (1), (2)")?;
+    assert_eq!(result.kind, dbc::StatementKind::Query);
+    assert_eq!(result.rows.len(), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_execute_script_with_trigger() -> Result<(), Error> {
+    let mut database = _prepare_sqlite_database()?;
+    let script = "
+        CREATE TABLE log (name TEXT);
+        CREATE TRIGGER log_insert AFTER INSERT ON test_table BEGIN
+            INSERT INTO log (name) VALUES (CASE WHEN new.name = '' THEN 'empty' ELSE new.name END);
+            INSERT INTO log (name) VALUES ('done');
+        END;
+        INSERT INTO test_table (name) VALUES ('test1');
+    ";
+    let results = database.execute_script(script)?;
+    assert_eq!(results.len(), 3);
+    assert_eq!(database.execute_query("SELECT * FROM log")?.rows.len(), 2);
+    database.execute_query("DROP TABLE log")?;
+    Ok(())
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_url_options() -> Result<(), Error> {
+    let directory = std::env::temp_dir().join(format!("rdbc2_sqlite_{}", std::process::id()));
+    std::fs::create_dir_all(&directory)?;
+    let path = directory.join("test.db");
+
+    // Absolute paths start with a third slash
+    let url = format!(
+        "sqlite://{}?journal_mode=wal&foreign_keys=on&busy_timeout=1000",
+        path.display()
+    );
+    let mut database = dbc::Database::new(&url)?;
+    assert!(path.exists());
+    database.execute_query("CREATE TABLE test_table (id INTEGER PRIMARY KEY, name TEXT)")?;
+    let result = database.execute_query("PRAGMA journal_mode")?;
+    assert_eq!(
+        result.rows[0].get_value(0),
+        Some(&dbc::Value::String("wal".to_owned()))
+    );
+    let result = database.execute_query("PRAGMA foreign_keys")?;
+    assert_eq!(result.rows[0].get_value(0), Some(&dbc::Value::Int(1)));
+    drop(database);
+
+    let mut database = dbc::Database::new(&format!("sqlite://{}?mode=ro", path.display()))?;
+    assert!(database
+        .execute_query("INSERT INTO test_table (name) VALUES ('test1')")
+        .is_err());
+    drop(database);
+
+    // file: URIs keep the options SQLite interprets itself
+    let url = format!("sqlite://file:{}?mode=ro&immutable=1", path.display());
+    let mut database = dbc::Database::new(&url)?;
+    assert!(database
+        .execute_query("SELECT * FROM test_table")?
+        .rows
+        .is_empty());
+    drop(database);
+    std::fs::remove_dir_all(&directory)?;
+
+    // Relative paths are relative to the working directory
+    let path = std::path::Path::new("target/rdbc2_relative.db");
+    drop(dbc::Database::new("sqlite://target/rdbc2_relative.db")?);
+    assert!(path.exists());
+    std::fs::remove_file(path)?;
+
+    // Connections to a named in-memory database with a shared cache see the same tables
+    let url = "sqlite://shared_test?mode=memory&cache=shared";
+    let mut first = dbc::Database::new(url)?;
+    let mut second = dbc::Database::new(url)?;
+    first.execute_query("CREATE TABLE test_table (id INTEGER PRIMARY KEY, name TEXT)")?;
+    assert!(second
+        .execute_query("SELECT * FROM test_table")?
+        .rows
+        .is_empty());
+
+    assert!(matches!(
+        dbc::Database::new("sqlite://:memory:?unknown=1"),
+        Err(dbc::Error::Configuration(_))
+    ));
+    assert!(dbc::Database::new("sqlite://:memory:?mode=invalid").is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_connect_options() -> Result<(), Error> {
+    let options = dbc::SqliteOptions::memory()
+        .foreign_keys(true)
+        .busy_timeout(std::time::Duration::from_millis(500));
+    assert_eq!(
+        options.to_string(),
+        "sqlite://:memory:?busy_timeout=500&foreign_keys=on"
+    );
+    let mut database = dbc::Database::connect(options)?;
+    let result = database.execute_query("PRAGMA foreign_keys")?;
+    assert_eq!(result.rows[0].get_value(0), Some(&dbc::Value::Int(1)));
+
+    // URLs are parsed into options and rendered back
+    let url = "sqlite://data.db?mode=ro&cache=shared&journal_mode=wal";
+    let options = url.parse::<dbc::ConnectOptions>()?;
+    let expected = dbc::SqliteOptions::new("data.db")
+        .mode(dbc::SqliteMode::ReadOnly)
+        .cache(dbc::SqliteCache::Shared)
+        .journal_mode(dbc::SqliteJournalMode::Wal);
+    assert_eq!(options, dbc::ConnectOptions::from(expected));
+    assert_eq!(options.to_string(), url);
+    let url = "sqlite://file:data.db?mode=ro&immutable=1";
+    assert_eq!(url.parse::<dbc::ConnectOptions>()?.to_string(), url);
+
+    // Paths are percent-decoded and encoded
+    let url = "sqlite:///tmp/my%20data%3F%23%25.db?mode=ro";
+    let options = url.parse::<dbc::ConnectOptions>()?;
+    let expected = dbc::SqliteOptions::new("/tmp/my data?#%.db").mode(dbc::SqliteMode::ReadOnly);
+    assert_eq!(options, dbc::ConnectOptions::from(expected));
+    assert_eq!(options.to_string(), url);
+    assert!("sqlite://data%2.db".parse::<dbc::ConnectOptions>().is_err());
+
+    assert!(dbc::Database::connect(dbc::SqliteOptions::new("")).is_err());
+
+    Ok(())
+}
+
+// A driver for `scratch://` URLs, each opening a fresh in-memory SQLite database
+struct ScratchDriver;
+
+impl dbc::Driver for ScratchDriver {
+    fn scheme(&self) -> &str {
+        "scratch"
+    }
+
+    fn connect(&self, _url: &str) -> Result<Box<dyn dbc::Connection + Send>, dbc::Error> {
+        let registry = dbc::DriverRegistry::builtin();
+        registry
+            .driver("sqlite")
+            .unwrap()
+            .connect(SQLITE_DATABASE_URL)
+    }
+}
+
+// A driver for `lazy://` URLs, registering the scratch driver when it first connects
+struct LazyDriver;
+
+impl dbc::Driver for LazyDriver {
+    fn scheme(&self) -> &str {
+        "lazy"
+    }
+
+    fn connect(&self, url: &str) -> Result<Box<dyn dbc::Connection + Send>, dbc::Error> {
+        dbc::register_driver(ScratchDriver);
+        ScratchDriver.connect(url)
+    }
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_sqlite_custom_driver() -> Result<(), Error> {
+    let mut registry = dbc::DriverRegistry::new();
+    assert!(matches!(
+        registry.connect(SQLITE_DATABASE_URL),
+        Err(dbc::Error::UnsupportedScheme(scheme)) if scheme == "sqlite"
+    ));
+    registry.register(ScratchDriver);
+    let mut database = registry.connect("scratch://test")?;
+    database.execute_query("CREATE TABLE test_table (id INTEGER PRIMARY KEY)")?;
+    assert!(registry.connect("scratch").is_err());
+
+    assert!(dbc::Database::new("scratch://test").is_err());
+    dbc::register_driver(ScratchDriver);
+    let mut database = dbc::Database::new("scratch://test")?;
+    let result = database.execute_query("SELECT 1")?;
+    assert_eq!(result.rows[0].get_value(0), Some(&dbc::Value::Int(1)));
+    // The built-in drivers stay registered
+    dbc::Database::new(SQLITE_DATABASE_URL)?;
+
+    // The global registry is not locked while a driver connects
+    dbc::register_driver(LazyDriver);
+    dbc::Database::new("lazy://test")?;
+
+    Ok(())
+}
+
+#[cfg(not(feature = "mysql"))]
+#[test]
+fn test_disabled_driver() {
+    let err = dbc::Database::new("mysql://localhost/test_db")
+        .err()
+        .unwrap();
+    assert!(matches!(err, dbc::Error::Configuration(_)), "{:?}", err);
+    assert!(err.to_string().contains("not compiled in"), "{}", err);
+}